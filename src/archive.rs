@@ -0,0 +1,178 @@
+use std::{
+    fs::{self, File},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Error, Result};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use log::info;
+use tempfile::TempDir;
+use zip::{write::SimpleFileOptions, DateTime, ZipArchive, ZipWriter};
+
+/// The kinds of archive a theme can be packaged as.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ArchiveFormat {
+    Zip,
+    TarGz,
+}
+
+impl ArchiveFormat {
+    /// Works out the format of an archive from its file name, if it's an archive at all.
+    pub fn for_path(path: &Path) -> Option<ArchiveFormat> {
+        let name = path.file_name()?.to_str()?.to_lowercase();
+        if name.ends_with(".zip") {
+            Some(ArchiveFormat::Zip)
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(ArchiveFormat::TarGz)
+        } else {
+            None
+        }
+    }
+}
+
+/// Extracts a theme archive so it can be loaded like a theme directory, returning the directory
+/// it's extracted to and the directory within it holding its `theme.json`. Archives are extracted
+/// into a new private directory each time, which is deleted once the `TempDir` is dropped, so
+/// nothing else can swap files into a theme between extracting and loading it.
+pub fn extract(path: &Path, format: ArchiveFormat) -> Result<(TempDir, PathBuf)> {
+    let temp = tempfile::Builder::new().prefix("pdxdoc-theme-").tempdir()?;
+    let dir = temp.path();
+    info!("extracting theme archive {:?} to {:?}", path, dir);
+
+    let file = File::open(path)?;
+    match format {
+        ArchiveFormat::Zip => ZipArchive::new(file)?.extract(dir)?,
+        ArchiveFormat::TarGz => tar::Archive::new(GzDecoder::new(file)).unpack(dir)?,
+    }
+
+    if dir.join("theme.json").is_file() {
+        let dir = dir.to_path_buf();
+        return Ok((temp, dir));
+    }
+
+    // archives made by zipping up a theme's folder have the theme one level down
+    let mut subdirs = fs::read_dir(dir)?
+        .map(|e| e.map(|e| e.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    subdirs.retain(|d| d.join("theme.json").is_file());
+    match subdirs.as_slice() {
+        [theme_dir] => Ok((temp, theme_dir.clone())),
+        _ => Err(Error::msg(format!(
+            "Can't find theme.json in theme archive {:?}",
+            path
+        ))),
+    }
+}
+
+/// Packs every file in a theme directory into an archive. Files are added in order with no
+/// timestamps, so packing the same theme twice gives the same archive. Hidden files, like `.git`,
+/// are left out, as is the archive itself if it's being written inside the theme.
+pub fn pack(dir: &Path, output: &Path, format: ArchiveFormat) -> Result<()> {
+    let mut files = vec![];
+    collect_files(dir, &mut files)?;
+    if let Ok(output) = output.canonicalize() {
+        files.retain(|path| path.canonicalize().map_or(true, |path| path != output));
+    }
+    files.sort();
+
+    let file = File::create(output)?;
+    match format {
+        ArchiveFormat::Zip => {
+            let mut zip = ZipWriter::new(file);
+            let options = SimpleFileOptions::default()
+                .last_modified_time(DateTime::default())
+                .unix_permissions(0o644);
+            for path in &files {
+                zip.start_file(archive_name(dir, path)?, options)?;
+                zip.write_all(&fs::read(path)?)?;
+            }
+            zip.finish()?;
+        }
+        ArchiveFormat::TarGz => {
+            let mut tar = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+            for path in &files {
+                let bytes = fs::read(path)?;
+                let mut header = tar::Header::new_gnu();
+                header.set_size(bytes.len() as u64);
+                header.set_mode(0o644);
+                header.set_mtime(0);
+                header.set_cksum();
+                tar.append_data(&mut header, archive_name(dir, path)?, bytes.as_slice())?;
+            }
+            tar.into_inner()?.finish()?;
+        }
+    }
+
+    info!(
+        "packed {} files from {:?} into {:?}",
+        files.len(),
+        dir,
+        output
+    );
+    Ok(())
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+
+        let path = entry.path();
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+/// The name of a file inside an archive, which always uses forward slashes.
+fn archive_name(dir: &Path, path: &Path) -> Result<String> {
+    Ok(path
+        .strip_prefix(dir)?
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{self, File};
+
+    use zip::ZipArchive;
+
+    use super::{pack, ArchiveFormat};
+
+    #[test]
+    fn pack_skips_hidden_files_and_its_own_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        for file in [
+            "theme.json",
+            "assets/style.scss",
+            ".DS_Store",
+            ".git/config",
+            "assets/.cache/style.css",
+        ] {
+            let path = dir.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, file).unwrap();
+        }
+
+        // packing twice, so the second time the archive from the first is in the directory
+        let output = dir.join("theme.zip");
+        pack(dir, &output, ArchiveFormat::Zip).unwrap();
+        pack(dir, &output, ArchiveFormat::Zip).unwrap();
+
+        let archive = ZipArchive::new(File::open(&output).unwrap()).unwrap();
+        let mut names = archive.file_names().collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, ["assets/style.scss", "theme.json"]);
+    }
+}
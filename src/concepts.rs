@@ -0,0 +1,161 @@
+use std::rc::Rc;
+
+use anyhow::Result;
+use clauser::data::script_doc_parser::doc_string::{DocString, DocStringSegment};
+use handlebars::html_escape;
+use itertools::Itertools;
+use log::{info, warn};
+
+use crate::{
+    dossier::Dossier,
+    entry::{DocEntry, DEFINED_IN_PROPERTY},
+    localization::Localization,
+    mods::{GameFiles, LayeredDefinitions, Origin},
+    page::PageContext,
+    script::{ScriptFile, ScriptNode},
+    util::{self, code_list},
+};
+
+/// A game concept, the glossary terms that tooltips link to.
+pub struct ConceptEntry {
+    id: u64,
+    name: String,
+    display_name: Option<String>,
+    description: Option<String>,
+    aliases: Vec<String>,
+    family: Option<String>,
+    path: String,
+    line: usize,
+    origin: Option<Origin>,
+}
+
+impl ConceptEntry {
+    pub fn category_id() -> u64 {
+        util::hash(&"CONCEPTS")
+    }
+
+    pub fn entry_id_for_name(name: &str) -> u64 {
+        util::hash(&format!("concept_{}", name))
+    }
+
+    fn new(name: &str, path: &str, node: &ScriptNode, localization: &Localization) -> ConceptEntry {
+        ConceptEntry {
+            id: Self::entry_id_for_name(name),
+            name: name.to_string(),
+            display_name: localization.text(name).map(|s| s.to_string()),
+            description: localization
+                .text(&format!("{}_desc", name))
+                .map(|s| s.to_string()),
+            aliases: node
+                .get("alias")
+                .map(|a| a.children().iter().filter_map(|c| c.scalar()))
+                .into_iter()
+                .flatten()
+                .map(|s| s.to_string())
+                .collect(),
+            family: node.get_scalar("family").map(|s| s.to_string()),
+            path: path.to_string(),
+            line: node.line,
+            origin: None,
+        }
+    }
+}
+
+impl DocEntry for ConceptEntry {
+    fn id(&self) -> u64 {
+        self.id
+    }
+
+    fn category_id(&self) -> Option<u64> {
+        Some(Self::category_id())
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn record_cross_references(&self, _dossier: &mut Dossier) {}
+
+    fn body(&self) -> Option<DocString> {
+        self.description.as_ref().map(|d| {
+            DocString::from(DocStringSegment::Text {
+                contents: html_escape(d),
+            })
+        })
+    }
+
+    fn origin(&self) -> Option<&Origin> {
+        self.origin.as_ref()
+    }
+
+    fn properties(
+        &self,
+        _context: &PageContext,
+        _dossier: Rc<Dossier>,
+    ) -> Vec<(String, DocString)> {
+        let mut properties = Vec::new();
+
+        if let Some(display_name) = &self.display_name {
+            properties.push((
+                "Display Name".into(),
+                DocString::from(DocStringSegment::Text {
+                    contents: html_escape(display_name),
+                }),
+            ));
+        }
+
+        if !self.aliases.is_empty() {
+            properties.push(("Aliases".into(), code_list(self.aliases.iter())));
+        }
+
+        if let Some(family) = &self.family {
+            properties.push(("Family".into(), code_list(std::iter::once(family))));
+        }
+
+        properties.push((
+            DEFINED_IN_PROPERTY.into(),
+            code_list(std::iter::once(&format!("{}:{}", self.path, self.line))),
+        ));
+
+        properties
+    }
+}
+
+/// Reads game concepts from `common/game_concepts` of a game and its mods.
+pub struct ConceptReader;
+
+impl ConceptReader {
+    pub fn read(files: &GameFiles, localization: &Localization) -> Result<Vec<ConceptEntry>> {
+        let mut concepts = LayeredDefinitions::new();
+
+        for file in files.find("common/game_concepts/**/*.txt")? {
+            let script = match ScriptFile::read(&file.disk_path, &file.path) {
+                Ok(script) => script,
+                Err(e) => {
+                    warn!("skipping concept file {}: {}", file.path, e);
+                    continue;
+                }
+            };
+
+            for node in script
+                .nodes
+                .iter()
+                .filter(|n| n.is_block() && n.key.is_some())
+            {
+                concepts.insert_with(&file, node.key(), || {
+                    ConceptEntry::new(node.key(), &file.path, node, localization)
+                });
+            }
+        }
+
+        let concepts = concepts
+            .into_definitions(files)
+            .into_iter()
+            .map(|(concept, origin)| ConceptEntry { origin, ..concept })
+            .sorted_by(|a, b| a.name.cmp(&b.name))
+            .collect_vec();
+
+        info!("read {} game concepts", concepts.len());
+        Ok(concepts)
+    }
+}
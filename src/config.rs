@@ -1,10 +1,12 @@
 use std::{
+    collections::{BTreeMap, HashMap},
     fs,
     path::{Path, PathBuf},
 };
 
 use anyhow::Result;
 use serde::Deserialize;
+use serde_json::Value;
 
 use crate::error::Error;
 
@@ -27,8 +29,35 @@ pub struct Profile {
     pub name: String,
     pub title: String,
     pub game: ProfileGame,
+    #[serde(default)]
     pub game_data_dir: String,
+    #[serde(default)]
     pub user_data_dir: String,
+    /// A snapshot file to load this profile from, instead of reading the game's files.
+    #[serde(default)]
+    pub snapshot: Option<String>,
+    /// Snapshots of older versions of the game, oldest first, used to show when entries were
+    /// introduced, changed and removed.
+    #[serde(default)]
+    pub history: Vec<String>,
+    /// Mod directories to layer over the game's files, in load order.
+    #[serde(default)]
+    pub mods: Vec<String>,
+    /// The language to read the game's localization in, like `english`.
+    #[serde(default = "default_localization_language")]
+    pub localization_language: String,
+    /// The name of another profile to compare this one against, generating changelog pages.
+    #[serde(default)]
+    pub compare_to: Option<String>,
+    /// The folder to put this profile's pages in, like `1.7`. Defaults to the profile's name.
+    #[serde(default)]
+    pub dir: Option<String>,
+}
+
+impl Profile {
+    pub fn dir(&self) -> &str {
+        self.dir.as_deref().unwrap_or(&self.name)
+    }
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -59,6 +88,18 @@ fn default_false() -> bool {
     false
 }
 
+fn default_usage_examples() -> usize {
+    3
+}
+
+fn default_localization_language() -> String {
+    "english".into()
+}
+
+fn default_language() -> String {
+    "en".into()
+}
+
 fn default_pagination() -> PaginationMode {
     PaginationMode::Absolute {
         limit: default_limit(),
@@ -74,6 +115,34 @@ pub struct Config {
     pub use_subfolder_for_single_profile: bool,
     #[serde(default = "default_pagination")]
     pub pagination: PaginationMode,
+    /// The maximum number of usage examples from the game's script files to show for each entry.
+    #[serde(default = "default_usage_examples")]
+    pub usage_examples: usize,
+    /// Whether to also give every entry a page of its own, like `effects/add_modifier.html`, which
+    /// links to the entry go to instead of its place in the category's pages.
+    #[serde(default = "default_false")]
+    pub entry_pages: bool,
+    /// The name of a profile to also publish under `latest/`, alongside every profile's own folder.
+    #[serde(default)]
+    pub latest: Option<String>,
+    /// The theme to use, as a directory or a `.zip` or `.tar.gz` archive. Defaults to the
+    /// built-in default theme.
+    #[serde(default)]
+    pub theme: Option<PathBuf>,
+    /// Values for the theme's settings, like `"accent_color": "#3b7dd8"`.
+    #[serde(default)]
+    pub theme_settings: BTreeMap<String, Value>,
+    /// Whether the theme may process its assets with programs it names, which run on this machine
+    /// with the same access as pdxdoc. Themes that use them fail to load unless this is set.
+    #[serde(default = "default_false")]
+    pub allow_theme_processors: bool,
+    /// The language to use the theme's strings in, which falls back to English for any strings
+    /// that haven't been translated.
+    #[serde(default = "default_language")]
+    pub language: String,
+    /// Replacements for the theme's strings, like `"no_information": "Nothing to see here."`.
+    #[serde(default)]
+    pub strings: HashMap<String, String>,
 }
 
 impl Config {
@@ -0,0 +1,417 @@
+use std::rc::Rc;
+
+use anyhow::Result;
+use clauser::data::script_doc_parser::{
+    doc_string::{DocString, DocStringSegment},
+    ScriptDocCategory, ScriptDocEntry,
+};
+use handlebars::html_escape;
+use itertools::Itertools;
+use log::{info, warn};
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::{
+    dossier::{DocCategory, Dossier},
+    entry::{DocEntry, DEFINED_IN_PROPERTY},
+    mods::{GameFiles, LayeredDefinitions, Origin},
+    page::PageContext,
+    script::{ScriptContext, ScriptFile, ScriptNode, ScriptReferenceKind, ScriptVocabulary},
+    util,
+};
+
+static PARAM_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"\$([A-Za-z0-9_]+)\$").unwrap());
+static PARAM_TAG_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^@param\s+\$?([A-Za-z0-9_]+)\$?\s*(.*)$").unwrap());
+
+/// The kinds of things that can be defined in script and documented with doc comments.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DefinitionKind {
+    ScriptedEffect,
+    ScriptedTrigger,
+    ScriptValue,
+    OnAction,
+}
+
+impl DefinitionKind {
+    pub const ALL: [DefinitionKind; 4] = [
+        DefinitionKind::ScriptedEffect,
+        DefinitionKind::ScriptedTrigger,
+        DefinitionKind::ScriptValue,
+        DefinitionKind::OnAction,
+    ];
+
+    /// The directory these definitions are read from, relative to the game or mod root.
+    fn dir(&self) -> &'static str {
+        match self {
+            DefinitionKind::ScriptedEffect => "common/scripted_effects",
+            DefinitionKind::ScriptedTrigger => "common/scripted_triggers",
+            DefinitionKind::ScriptValue => "common/script_values",
+            DefinitionKind::OnAction => "common/on_actions",
+        }
+    }
+
+    fn key(&self) -> &'static str {
+        match self {
+            DefinitionKind::ScriptedEffect => "scripted_effects",
+            DefinitionKind::ScriptedTrigger => "scripted_triggers",
+            DefinitionKind::ScriptValue => "script_values",
+            DefinitionKind::OnAction => "script_on_actions",
+        }
+    }
+
+    fn display_name(&self) -> &'static str {
+        match self {
+            DefinitionKind::ScriptedEffect => "Scripted Effects",
+            DefinitionKind::ScriptedTrigger => "Scripted Triggers",
+            DefinitionKind::ScriptValue => "Script Values",
+            DefinitionKind::OnAction => "Scripted On Actions",
+        }
+    }
+
+    pub fn category_id(&self) -> u64 {
+        util::hash(&self.key())
+    }
+
+    pub fn category(&self) -> DocCategory {
+        DocCategory::from_id(self.category_id(), self.key(), self.display_name())
+    }
+
+    pub fn entry_id_for_name(&self, name: &str) -> u64 {
+        util::hash(&format!("{}_{}", self.key(), name))
+    }
+}
+
+/// A documented parameter of a scripted effect or trigger.
+pub struct DefinitionParam {
+    name: String,
+    description: Option<String>,
+}
+
+/// A definition read from a script file, before we know what it references.
+pub struct ParsedDefinition {
+    pub kind: DefinitionKind,
+    pub name: String,
+    pub path: String,
+    pub origin: Option<Origin>,
+    node: ScriptNode,
+}
+
+/// A scripted effect, scripted trigger, script value or on_action defined in script.
+pub struct ScriptDefinitionEntry {
+    id: u64,
+    kind: DefinitionKind,
+    name: String,
+    path: String,
+    line: usize,
+    description: Vec<String>,
+    params: Vec<DefinitionParam>,
+    effects: Vec<u64>,
+    triggers: Vec<u64>,
+    extends: Option<u64>,
+    origin: Option<Origin>,
+}
+
+impl ScriptDefinitionEntry {
+    pub fn kind(&self) -> DefinitionKind {
+        self.kind
+    }
+
+    pub fn new(
+        definition: ParsedDefinition,
+        vocabulary: &ScriptVocabulary,
+        dossier: &Dossier,
+    ) -> ScriptDefinitionEntry {
+        let ParsedDefinition {
+            kind,
+            name,
+            path,
+            origin,
+            node,
+        } = definition;
+
+        let (description, mut params) = Self::parse_doc_comment(&node.comments);
+
+        // any $PARAM$ that isn't documented is still a parameter
+        let mut placeholders = Vec::new();
+        Self::collect_placeholders(&node, &mut placeholders);
+        for placeholder in placeholders.into_iter().unique() {
+            if !params.iter().any(|p| p.name == placeholder) {
+                params.push(DefinitionParam {
+                    name: placeholder,
+                    description: None,
+                });
+            }
+        }
+
+        let mut effects = Vec::new();
+        let mut triggers = Vec::new();
+        let mut visit = |r: crate::script::ScriptReference| match r.kind {
+            ScriptReferenceKind::Effect => effects.push(r.id),
+            ScriptReferenceKind::Trigger => triggers.push(r.id),
+            _ => (),
+        };
+
+        match kind {
+            DefinitionKind::ScriptedEffect => {
+                vocabulary.walk(node.children(), ScriptContext::Effect, &mut visit)
+            }
+            DefinitionKind::ScriptedTrigger => {
+                vocabulary.walk(node.children(), ScriptContext::Trigger, &mut visit)
+            }
+            DefinitionKind::ScriptValue | DefinitionKind::OnAction => {
+                vocabulary.walk_unscoped(node.children(), &mut visit)
+            }
+        }
+
+        let id = kind.entry_id_for_name(&name);
+
+        // on_actions defined in script often hook into ones fired by the engine
+        let extends = match kind {
+            DefinitionKind::OnAction => dossier
+                .entries
+                .values()
+                .filter_map(|e| e.as_any().downcast_ref::<ScriptDocEntry>())
+                .find(|e| e.category == ScriptDocCategory::OnActions && e.name == name)
+                .map(|e| e.id),
+            _ => None,
+        };
+
+        ScriptDefinitionEntry {
+            id,
+            kind,
+            name,
+            path,
+            line: node.line,
+            description,
+            params,
+            effects: effects.into_iter().filter(|e| *e != id).unique().collect(),
+            triggers: triggers.into_iter().filter(|t| *t != id).unique().collect(),
+            extends,
+            origin,
+        }
+    }
+
+    /// Splits a doc comment into paragraphs of description and `@param` tags.
+    fn parse_doc_comment(comments: &[String]) -> (Vec<String>, Vec<DefinitionParam>) {
+        let mut paragraphs: Vec<String> = Vec::new();
+        let mut params: Vec<DefinitionParam> = Vec::new();
+        let mut current = String::new();
+
+        for line in comments {
+            let line = line.trim();
+
+            // skip decorative lines like `#####` or `# -----`
+            if !line.is_empty() && line.chars().all(|c| matches!(c, '#' | '-' | '=' | '*')) {
+                continue;
+            }
+
+            if let Some(captures) = PARAM_TAG_REGEX.captures(line) {
+                let description = captures[2].trim();
+                params.push(DefinitionParam {
+                    name: captures[1].to_string(),
+                    description: match description.is_empty() {
+                        true => None,
+                        false => Some(description.to_string()),
+                    },
+                });
+                continue;
+            }
+
+            if line.is_empty() {
+                if !current.is_empty() {
+                    paragraphs.push(std::mem::take(&mut current));
+                }
+                continue;
+            }
+
+            // lines following a @param tag continue its description
+            if let (Some(param), true) = (params.last_mut(), current.is_empty()) {
+                let description = param.description.get_or_insert_with(String::new);
+                if !description.is_empty() {
+                    description.push(' ');
+                }
+                description.push_str(line);
+                continue;
+            }
+
+            if !current.is_empty() {
+                current.push(' ');
+            }
+            current.push_str(line);
+        }
+
+        if !current.is_empty() {
+            paragraphs.push(current);
+        }
+
+        (paragraphs, params)
+    }
+
+    fn collect_placeholders(node: &ScriptNode, placeholders: &mut Vec<String>) {
+        for text in node.key.iter().map(|k| k.as_str()).chain(node.scalar()) {
+            for captures in PARAM_REGEX.captures_iter(text) {
+                placeholders.push(captures[1].to_string());
+            }
+        }
+
+        for child in node.children() {
+            Self::collect_placeholders(child, placeholders);
+        }
+    }
+
+    fn links(&self, context: &PageContext, dossier: &Dossier, ids: &[u64]) -> DocString {
+        let mut links = ids
+            .iter()
+            .filter_map(|id| dossier.entries.get(id))
+            .map(|e| (e.name().to_string(), e.id()))
+            .collect_vec();
+        links.sort();
+
+        DocString::new_from_iter(
+            links
+                .iter()
+                .map(|(name, id)| dossier.link_for_entry(context, name, id)),
+            Some(", "),
+        )
+    }
+}
+
+impl DocEntry for ScriptDefinitionEntry {
+    fn id(&self) -> u64 {
+        self.id
+    }
+
+    fn category_id(&self) -> Option<u64> {
+        Some(self.kind.category_id())
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    // named for how they read on the page of the entry they point to
+    fn record_cross_references(&self, dossier: &mut Dossier) {
+        let used_by = format!("Used By {}", self.kind.display_name());
+        for effect in &self.effects {
+            dossier.add_reference(&used_by, self.id, *effect);
+        }
+
+        for trigger in &self.triggers {
+            dossier.add_reference(&used_by, self.id, *trigger);
+        }
+
+        if let Some(extends) = self.extends {
+            dossier.add_reference("Extended By", self.id, extends);
+        }
+    }
+
+    fn body(&self) -> Option<DocString> {
+        if self.description.is_empty() {
+            return None;
+        }
+
+        Some(util::paragraphs(
+            self.description.iter().map(|p| html_escape(p)),
+        ))
+    }
+
+    fn origin(&self) -> Option<&Origin> {
+        self.origin.as_ref()
+    }
+
+    fn properties(&self, context: &PageContext, dossier: Rc<Dossier>) -> Vec<(String, DocString)> {
+        let mut properties = Vec::new();
+
+        if !self.params.is_empty() {
+            properties.push((
+                "Parameters".into(),
+                DocString::new_from_iter(
+                    self.params.iter().map(|p| DocStringSegment::Text {
+                        contents: match &p.description {
+                            Some(description) => format!(
+                                "<code>${}$</code> &ndash; {}",
+                                html_escape(&p.name),
+                                html_escape(description)
+                            ),
+                            None => format!("<code>${}$</code>", html_escape(&p.name)),
+                        },
+                    }),
+                    Some("<br/>"),
+                ),
+            ));
+        }
+
+        if let Some(extends) = self.extends {
+            properties.push((
+                "Extends".into(),
+                dossier.link_for_entry(context, &self.name, &extends).into(),
+            ));
+        }
+
+        properties.push((
+            "Triggers Used".into(),
+            self.links(context, &dossier, &self.triggers),
+        ));
+        properties.push((
+            "Effects Used".into(),
+            self.links(context, &dossier, &self.effects),
+        ));
+        properties.push((
+            DEFINED_IN_PROPERTY.into(),
+            DocString::from(DocStringSegment::Text {
+                contents: format!("<code>{}:{}</code>", html_escape(&self.path), self.line),
+            }),
+        ));
+
+        properties
+    }
+}
+
+/// Reads scripted effects, scripted triggers, script values and on_actions from the game and its mods.
+pub struct DefinitionReader;
+
+impl DefinitionReader {
+    pub fn read(files: &GameFiles) -> Result<Vec<ParsedDefinition>> {
+        let mut definitions = Vec::new();
+
+        for kind in DefinitionKind::ALL {
+            let mut layered = LayeredDefinitions::new();
+            for file in files.find(&format!("{}/**/*.txt", kind.dir()))? {
+                let script = match ScriptFile::read(&file.disk_path, &file.path) {
+                    Ok(script) => script,
+                    Err(e) => {
+                        warn!("skipping script file {}: {}", file.path, e);
+                        continue;
+                    }
+                };
+
+                for node in script.nodes {
+                    // script values can also be plain numbers
+                    if node.key.is_none()
+                        || (!node.is_block() && kind != DefinitionKind::ScriptValue)
+                    {
+                        continue;
+                    }
+
+                    let name = node.key().to_string();
+                    layered.insert_with(&file, &name, || (file.path.clone(), node));
+                }
+            }
+
+            definitions.extend(layered.into_definitions(files).into_iter().map(
+                |((path, node), origin)| ParsedDefinition {
+                    kind,
+                    name: node.key().to_string(),
+                    path,
+                    origin,
+                    node,
+                },
+            ));
+        }
+
+        info!("read {} script definitions", definitions.len());
+
+        Ok(definitions)
+    }
+}
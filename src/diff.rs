@@ -0,0 +1,199 @@
+use std::{collections::HashMap, rc::Rc};
+
+use anyhow::Result;
+use itertools::Itertools;
+use log::info;
+
+use crate::{
+    dossier::Dossier,
+    entry::DEFINED_IN_PROPERTY,
+    record::{EntryRecord, RecordValue},
+};
+
+/// Properties that aren't compared, since they change whenever the game's files are edited around
+/// an entry without the entry itself changing. Usage counts aren't compared either, but they're
+/// never recorded as properties in the first place.
+const UNCOMPARED_PROPERTIES: [&str; 1] = [DEFINED_IN_PROPERTY];
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// A single field of an entry that differs between two versions. A missing value means the field
+/// didn't exist, or was empty, in that version.
+pub struct FieldChange {
+    pub name: String,
+    pub old: Option<RecordValue>,
+    pub new: Option<RecordValue>,
+}
+
+impl FieldChange {
+    /// If both versions of this field are lists of links, returns the names that were added and removed.
+    pub fn list_changes(&self) -> Option<(Vec<&str>, Vec<&str>)> {
+        let old = match &self.old {
+            Some(old) => old.link_names()?,
+            None => vec![],
+        };
+        let new = match &self.new {
+            Some(new) => new.link_names()?,
+            None => vec![],
+        };
+
+        let added = new.iter().filter(|n| !old.contains(n)).copied().collect();
+        let removed = old.iter().filter(|o| !new.contains(o)).copied().collect();
+        Some((added, removed))
+    }
+}
+
+/// An entry that was added, removed or changed between two versions.
+pub struct EntryChange {
+    pub kind: ChangeKind,
+    pub category: String,
+    pub category_display_name: String,
+    pub name: String,
+    /// The ID of the entry in the newer dossier, or in the older one if it was removed.
+    pub id: u64,
+    pub fields: Vec<FieldChange>,
+}
+
+/// The differences between two versions of a dossier, entry by entry.
+pub struct DossierDiff {
+    pub old_version: String,
+    pub new_version: String,
+    pub changes: Vec<EntryChange>,
+}
+
+impl DossierDiff {
+    /// Compares two loaded dossiers.
+    pub fn between(old: Rc<Dossier>, new: Rc<Dossier>) -> Result<DossierDiff> {
+        Ok(DossierDiff::new(
+            &EntryRecord::record_all(old.clone())?,
+            &EntryRecord::record_all(new.clone())?,
+            old.info.game_version(),
+            new.info.game_version(),
+        ))
+    }
+
+    /// Compares two sets of entry records. Entries are matched up by category and name.
+    pub fn new(
+        old: &[EntryRecord],
+        new: &[EntryRecord],
+        old_version: &str,
+        new_version: &str,
+    ) -> DossierDiff {
+        let old_by_key: HashMap<(&str, &str), &EntryRecord> = old
+            .iter()
+            .map(|r| ((r.category.as_str(), r.name.as_str()), r))
+            .collect();
+        let new_by_key: HashMap<(&str, &str), &EntryRecord> = new
+            .iter()
+            .map(|r| ((r.category.as_str(), r.name.as_str()), r))
+            .collect();
+
+        let mut changes = Vec::new();
+        for record in new {
+            match old_by_key.get(&(record.category.as_str(), record.name.as_str())) {
+                None => changes.push(Self::change(ChangeKind::Added, record, vec![])),
+                Some(old) => {
+                    let fields = Self::compare(old, record);
+                    if !fields.is_empty() {
+                        changes.push(Self::change(ChangeKind::Changed, record, fields));
+                    }
+                }
+            }
+        }
+
+        for record in old {
+            if !new_by_key.contains_key(&(record.category.as_str(), record.name.as_str())) {
+                changes.push(Self::change(ChangeKind::Removed, record, vec![]));
+            }
+        }
+
+        changes.sort_by(|a, b| a.category.cmp(&b.category).then(a.name.cmp(&b.name)));
+
+        info!(
+            "found {} changes between {} and {}",
+            changes.len(),
+            old_version,
+            new_version
+        );
+
+        DossierDiff {
+            old_version: old_version.to_string(),
+            new_version: new_version.to_string(),
+            changes,
+        }
+    }
+
+    /// Returns the name and display name of every category with changes.
+    pub fn categories(&self) -> Vec<(&str, &str)> {
+        self.changes
+            .iter()
+            .map(|c| (c.category.as_str(), c.category_display_name.as_str()))
+            .unique()
+            .collect()
+    }
+
+    pub fn changes_in<'a>(&'a self, category: &'a str) -> impl Iterator<Item = &'a EntryChange> {
+        self.changes.iter().filter(move |c| c.category == category)
+    }
+
+    fn change(kind: ChangeKind, record: &EntryRecord, fields: Vec<FieldChange>) -> EntryChange {
+        EntryChange {
+            kind,
+            category: record.category.clone(),
+            category_display_name: record.category_display_name.clone(),
+            name: record.name.clone(),
+            id: record.id,
+            fields,
+        }
+    }
+
+    fn compare(old: &EntryRecord, new: &EntryRecord) -> Vec<FieldChange> {
+        let non_empty = |v: Option<&RecordValue>| v.filter(|v| !v.is_empty()).cloned();
+
+        let mut fields = Vec::new();
+        let (old_body, new_body) = (non_empty(old.body.as_ref()), non_empty(new.body.as_ref()));
+        if old_body.as_ref().map(|v| v.text()) != new_body.as_ref().map(|v| v.text()) {
+            fields.push(FieldChange {
+                name: "Description".into(),
+                old: old_body,
+                new: new_body,
+            });
+        }
+
+        let names = new
+            .properties
+            .iter()
+            .chain(old.properties.iter())
+            .map(|(name, _)| name)
+            .filter(|name| !UNCOMPARED_PROPERTIES.contains(&name.as_str()))
+            .unique();
+
+        for name in names {
+            let find = |record: &EntryRecord| {
+                non_empty(
+                    record
+                        .properties
+                        .iter()
+                        .find(|(n, _)| n == name)
+                        .map(|(_, v)| v),
+                )
+            };
+
+            let (old_value, new_value) = (find(old), find(new));
+            if old_value.as_ref().map(|v| v.text()) != new_value.as_ref().map(|v| v.text()) {
+                fields.push(FieldChange {
+                    name: name.clone(),
+                    old: old_value,
+                    new: new_value,
+                });
+            }
+        }
+
+        fields
+    }
+}
@@ -1,4 +1,8 @@
-use std::{cell::RefCell, collections::HashMap, hash::Hash, rc::Rc};
+use std::{
+    cell::{OnceCell, RefCell},
+    collections::HashMap,
+    rc::Rc,
+};
 
 use anyhow::{Error, Result};
 use clauser::{
@@ -13,14 +17,19 @@ use crate::{
     config::{Config, Profile},
     entry::DocEntry,
     games::GameVersion,
+    history::HistoryIndex,
+    localization::Localization,
     mapper::SiteMapper,
     page::{
-        CategoryListPage, GenericListPage, MaskPage, Page, PageBuilder, PageContext, ScopePage,
+        CategoryListPage, EntryPage, GenericListPage, MaskPage, Page, PageBuilder, PageContext,
+        ScopePage, TombstonePage,
     },
-    util::{self, paginate, DocStringSer},
+    theme::Strings,
+    usage::UsageIndex,
+    util::{self, paginate, DocStringSer, StableHash, StableHasher},
 };
 
-#[derive(Clone, Hash)]
+#[derive(Clone)]
 pub struct DocCategory {
     id: u64,
     pub name: String,
@@ -28,8 +37,16 @@ pub struct DocCategory {
     pub entries: Vec<u64>,
 }
 
+/// Categories are hashed by their ID, so pages derived from a category keep their IDs as entries
+/// are added to it.
+impl StableHash for DocCategory {
+    fn stable_hash(&self, hasher: &mut StableHasher) {
+        self.id.stable_hash(hasher);
+    }
+}
+
 impl DocCategory {
-    pub fn new<T: Hash>(id: &T, name: &str, display_name: &str) -> DocCategory {
+    pub fn new<T: StableHash>(id: &T, name: &str, display_name: &str) -> DocCategory {
         DocCategory {
             id: util::hash(id),
             name: name.to_string(),
@@ -37,6 +54,20 @@ impl DocCategory {
             entries: Vec::new(),
         }
     }
+
+    /// Creates a category from an ID that's already been hashed.
+    pub fn from_id(id: u64, name: &str, display_name: &str) -> DocCategory {
+        DocCategory {
+            id,
+            name: name.to_string(),
+            display_name: display_name.to_string(),
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn id(&self) -> u64 {
+        self.id
+    }
 }
 
 #[derive(Deserialize, Serialize, Clone)]
@@ -53,6 +84,14 @@ pub struct DocInfo {
 }
 
 impl DocInfo {
+    pub fn game_version(&self) -> &str {
+        &self.version.game.version_number
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
     pub fn new(profile: &Profile, game_version: GameVersion) -> DocInfo {
         DocInfo {
             title: profile.title.clone(),
@@ -65,10 +104,11 @@ impl DocInfo {
     }
 }
 
+#[derive(Deserialize, Serialize, Clone)]
 pub struct CrossReference {
-    from_id: u64,
-    from_property: String,
-    to_id: u64,
+    pub from_id: u64,
+    pub from_property: String,
+    pub to_id: u64,
 }
 
 #[derive(Serialize)]
@@ -101,6 +141,13 @@ pub struct Dossier {
 
     cross_references: Vec<CrossReference>,
     pub info: DocInfo,
+    pub usages: UsageIndex,
+    /// The game's localization, with any mods layered on top.
+    pub localization: Localization,
+    /// How entries have changed across older versions, set once the dossier is complete.
+    pub history: OnceCell<HistoryIndex>,
+    /// The theme's strings, set once a theme is loaded to render the dossier with.
+    pub strings: OnceCell<Rc<Strings>>,
 }
 
 impl Dossier {
@@ -120,9 +167,39 @@ impl Dossier {
             string_table,
             mapper,
             builders: Vec::new(),
+            usages: UsageIndex::default(),
+            localization: Localization::default(),
+            history: OnceCell::new(),
+            strings: OnceCell::new(),
+        }
+    }
+
+    pub fn category(&self, id: u64) -> Option<&DocCategory> {
+        self.categories.get(&id)
+    }
+
+    pub fn categories(&self) -> impl Iterator<Item = &DocCategory> {
+        self.categories.values()
+    }
+
+    /// A string from the theme, for the text pdxdoc writes into pages itself rather than leaving
+    /// to templates, like page titles.
+    pub fn string(&self, key: &str, args: &[(&str, &str)]) -> String {
+        match self.strings.get() {
+            Some(strings) => strings.text(key, args),
+            None => Strings::default().text(key, args),
         }
     }
 
+    pub fn cross_references(&self) -> &[CrossReference] {
+        &self.cross_references
+    }
+
+    /// A context for recording this dossier's entries, rather than rendering them.
+    pub fn recording_context(&self) -> PageContext {
+        PageContext::new(self.mapper.clone())
+    }
+
     pub fn add_entries<T>(&mut self, entries: impl Iterator<Item = T>) -> Result<()>
     where
         T: DocEntry + 'static,
@@ -159,34 +236,72 @@ impl Dossier {
     pub fn create_pages(dossier: Rc<Dossier>, config: &Config) -> Vec<Box<dyn Page>> {
         let mut pages: Vec<Box<dyn Page>> = Vec::new();
 
-        for category in dossier.categories.values() {
+        // categories are sorted so pages are always created in the same order
+        let categories = dossier
+            .categories
+            .values()
+            .sorted_by(|a, b| a.name.cmp(&b.name));
+        for category in categories {
             let mut entries = category.entries.clone();
             entries.sort_by_key(|f| dossier.entries.get(f).unwrap().name());
             let mut page = 0;
+            let category_pages = paginate(
+                &config.pagination,
+                1,
+                entries.as_slice(),
+                |num_pages, entries| {
+                    page += 1;
+                    CategoryListPage::new(
+                        category.clone(),
+                        entries,
+                        (page, num_pages),
+                        dossier.clone(),
+                    )
+                },
+            );
+
+            // added after the category's pages, so links to entries go to their own pages instead
+            let entry_pages = match config.entry_pages {
+                true => {
+                    let file_names = EntryPage::file_names(
+                        entries
+                            .iter()
+                            .map(|e| (*e, dossier.entries.get(e).unwrap().name())),
+                    );
+                    category_pages
+                        .iter()
+                        .flat_map(|p| {
+                            p.entries().into_iter().map(|e| {
+                                Box::new(EntryPage::new(
+                                    dossier.clone(),
+                                    category,
+                                    e,
+                                    p.id(),
+                                    file_names[&e].clone(),
+                                )) as Box<dyn Page>
+                            })
+                        })
+                        .collect_vec()
+                }
+                false => vec![],
+            };
+
             pages.extend(
-                paginate(
-                    &config.pagination,
-                    1,
-                    entries.as_slice(),
-                    |num_pages, entries| {
-                        page += 1;
-                        CategoryListPage::new(
-                            category.clone(),
-                            entries,
-                            (page, num_pages),
-                            dossier.clone(),
-                        )
-                    },
-                )
-                .into_iter()
-                .map(|p| Box::new(p) as Box<dyn Page>),
+                category_pages
+                    .into_iter()
+                    .map(|p| Box::new(p) as Box<dyn Page>),
             );
+            pages.extend(entry_pages);
         }
 
         for builder in &dossier.builders {
             pages.extend(builder.build_pages(dossier.clone(), config).into_iter());
         }
 
+        if let Some(history) = dossier.history.get() {
+            pages.extend(TombstonePage::create(dossier.clone(), history));
+        }
+
         pages
     }
 
@@ -206,8 +321,6 @@ impl Dossier {
         page_id: u64,
         item: u64,
     ) -> CollatedCrossReferences {
-        let entry = dossier.entries.get(&item).unwrap();
-
         let mut groups = HashMap::new();
         for CrossReference {
             ref from_id,
@@ -219,7 +332,6 @@ impl Dossier {
                 dossier.clone(),
                 context,
                 &mut groups,
-                entry.as_ref(),
                 *from_id,
                 from_property,
             );
@@ -266,7 +378,6 @@ impl Dossier {
         dossier: Rc<Dossier>,
         context: &PageContext,
         groups: &mut HashMap<String, HashMap<String, Vec<DocStringSegment>>>,
-        entry: &dyn DocEntry,
         other_id: u64,
         prop: &str,
     ) {
@@ -283,40 +394,24 @@ impl Dossier {
 
         let prop_name = util::humanize_camel_case(&prop);
         let property = group.entry(prop_name).or_insert_with(|| Vec::new());
-        property.push(dossier.link_for_entry(context, entry, other.name(), &other.id()));
+        property.push(dossier.link_for_entry(context, other.name(), &other.id()));
     }
 
-    pub fn link_for_scope(
-        &self,
-        context: &PageContext,
-        from: &dyn DocEntry,
-        scope: &usize,
-    ) -> DocStringSegment {
+    pub fn link_for_scope(&self, context: &PageContext, scope: &usize) -> DocStringSegment {
         let scope = self.string_table.get(*scope).unwrap();
         let id = ScopePage::entry_id_for_name(&scope);
-        self.link_for_entry(context, from, &scope, &id)
+        self.link_for_entry(context, &scope, &id)
     }
 
-    pub fn link_for_mask(
-        &self,
-        context: &PageContext,
-        from: &dyn DocEntry,
-        mask: &usize,
-    ) -> DocStringSegment {
+    pub fn link_for_mask(&self, context: &PageContext, mask: &usize) -> DocStringSegment {
         let mask = self.string_table.get(*mask).unwrap();
         let id = MaskPage::entry_id_for_name(&mask);
-        self.link_for_entry(context, from, &mask, &id)
+        self.link_for_entry(context, &mask, &id)
     }
 
-    fn link_for_entry(
-        &self,
-        context: &PageContext,
-        from: &dyn DocEntry,
-        name: &str,
-        id: &u64,
-    ) -> DocStringSegment {
+    pub fn link_for_entry(&self, context: &PageContext, name: &str, id: &u64) -> DocStringSegment {
         if let Some(entry) = self.entries.get(&id) {
-            let url = context.url_for_entry(from, entry.as_ref());
+            let url = context.url_for_entry(entry.as_ref());
             return DocStringSegment::Link {
                 contents: name.to_owned(),
                 url: url,
@@ -345,7 +440,7 @@ impl Dossier {
         );
     }
 
-    fn add_reference(&mut self, prop: &str, this_id: u64, that_id: u64) {
+    pub fn add_reference(&mut self, prop: &str, this_id: u64, that_id: u64) {
         self.cross_references.push(CrossReference {
             from_id: this_id,
             from_property: prop.to_owned(),
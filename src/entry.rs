@@ -9,6 +9,7 @@ use once_cell::sync::Lazy;
 
 use crate::{
     dossier::Dossier,
+    mods::Origin,
     page::PageContext,
     util::{self, humanize_camel_case},
 };
@@ -23,6 +24,9 @@ impl<T: 'static> AsAny for T {
     }
 }
 
+/// The name of the property saying where an entry is defined, like `events/my_events.txt:12`.
+pub const DEFINED_IN_PROPERTY: &str = "Defined In";
+
 pub trait DocEntry: AsAny {
     fn id(&self) -> u64;
     fn category_id(&self) -> Option<u64>;
@@ -30,6 +34,10 @@ pub trait DocEntry: AsAny {
     fn record_cross_references(&self, dossier: &mut Dossier);
     fn body(&self) -> Option<DocString>;
     fn properties(&self, context: &PageContext, dossier: Rc<Dossier>) -> Vec<(String, DocString)>;
+    /// Where this entry comes from when mods are layered over the game, if it's read from game files.
+    fn origin(&self) -> Option<&Origin> {
+        None
+    }
 }
 
 pub struct EmptyDocEntry {
@@ -53,8 +61,10 @@ impl DocEntry for EmptyDocEntry {
         self.id
     }
 
+    // scopes and masks have no category of their own in the dossier, but snapshots still need to
+    // know which list page they belong to so they can be rebuilt
     fn category_id(&self) -> Option<u64> {
-        None
+        Some(self.category_id)
     }
 
     fn name(&self) -> &str {
@@ -182,7 +192,7 @@ impl DocEntry for ScriptDocEntry {
                 vec![
                     (
                         "Scope".into(),
-                        dossier.link_for_scope(context, self, scope).into(),
+                        dossier.link_for_scope(context, scope).into(),
                     ),
                     ("Random Valid?".into(), (*random_valid).into()),
                     (
@@ -206,7 +216,7 @@ impl DocEntry for ScriptDocEntry {
                     DocString::new_from_iter(
                         supported_scopes
                             .iter()
-                            .map(|s| dossier.link_for_scope(context, self, s)),
+                            .map(|s| dossier.link_for_scope(context, s)),
                         Some(", "),
                     ),
                 ),
@@ -215,7 +225,7 @@ impl DocEntry for ScriptDocEntry {
                     DocString::new_from_iter(
                         supported_targets
                             .iter()
-                            .map(|s| dossier.link_for_scope(context, self, s)),
+                            .map(|s| dossier.link_for_scope(context, s)),
                         Some(", "),
                     ),
                 ),
@@ -236,7 +246,7 @@ impl DocEntry for ScriptDocEntry {
                     DocString::new_from_iter(
                         input_scopes
                             .iter()
-                            .map(|s| dossier.link_for_scope(context, self, s)),
+                            .map(|s| dossier.link_for_scope(context, s)),
                         Some(", "),
                     ),
                 ),
@@ -245,7 +255,7 @@ impl DocEntry for ScriptDocEntry {
                     DocString::new_from_iter(
                         output_scopes
                             .iter()
-                            .map(|s| dossier.link_for_scope(context, self, s)),
+                            .map(|s| dossier.link_for_scope(context, s)),
                         Some(", "),
                     ),
                 ),
@@ -258,10 +268,7 @@ impl DocEntry for ScriptDocEntry {
                     properties.push(("Display Name".into(), display_name.clone()));
                 }
 
-                properties.push((
-                    "Mask".into(),
-                    dossier.link_for_mask(context, self, mask).into(),
-                ));
+                properties.push(("Mask".into(), dossier.link_for_mask(context, mask).into()));
                 properties
             }
             ScriptDocContent::OnActions {
@@ -270,7 +277,7 @@ impl DocEntry for ScriptDocEntry {
             } => vec![
                 (
                     "Expected Scope".into(),
-                    dossier.link_for_scope(context, self, expected_scope).into(),
+                    dossier.link_for_scope(context, expected_scope).into(),
                 ),
                 ("From Code".into(), (*from_code).into()),
             ],
@@ -284,7 +291,7 @@ impl DocEntry for ScriptDocEntry {
                     DocString::new_from_iter(
                         supported_scopes
                             .iter()
-                            .map(|s| dossier.link_for_scope(context, self, s)),
+                            .map(|s| dossier.link_for_scope(context, s)),
                         Some(", "),
                     ),
                 ),
@@ -293,7 +300,7 @@ impl DocEntry for ScriptDocEntry {
                     DocString::new_from_iter(
                         supported_targets
                             .iter()
-                            .map(|s| dossier.link_for_scope(context, self, s)),
+                            .map(|s| dossier.link_for_scope(context, s)),
                         Some(", "),
                     ),
                 ),
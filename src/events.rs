@@ -0,0 +1,444 @@
+use std::{collections::HashMap, rc::Rc};
+
+use anyhow::Result;
+use clauser::data::script_doc_parser::{
+    doc_string::{DocString, DocStringSegment},
+    ScriptDocCategory, ScriptDocEntry,
+};
+use handlebars::html_escape;
+use itertools::Itertools;
+use log::{info, warn};
+
+use crate::{
+    dossier::Dossier,
+    entry::{DocEntry, DEFINED_IN_PROPERTY},
+    localization::Localization,
+    mods::{GameFiles, LayeredDefinitions, Origin},
+    page::PageContext,
+    script::{ScriptContext, ScriptFile, ScriptNode, ScriptReferenceKind, ScriptVocabulary},
+    util::{self, code_list},
+};
+
+pub struct EventEntry {
+    id: u64,
+    name: String,
+    namespace: Option<String>,
+    event_type: Option<String>,
+    hidden: bool,
+    path: String,
+    line: usize,
+    titles: Vec<String>,
+    descs: Vec<String>,
+    options: Vec<String>,
+    effects: Vec<u64>,
+    triggers: Vec<u64>,
+    on_actions: Vec<(String, Option<u64>)>,
+    origin: Option<Origin>,
+}
+
+impl EventEntry {
+    pub fn category_id() -> u64 {
+        util::hash(&"EVENTS")
+    }
+
+    pub fn entry_id_for_name(name: &str) -> u64 {
+        util::hash(&format!("event_{}", name))
+    }
+
+    fn new(
+        name: &str,
+        namespace: Option<String>,
+        path: &str,
+        node: &ScriptNode,
+        vocabulary: &ScriptVocabulary,
+        on_actions: Vec<(String, Option<u64>)>,
+    ) -> EventEntry {
+        let mut titles = Vec::new();
+        let mut descs = Vec::new();
+        collect_loc_keys(node.children(), "title", &mut titles);
+        collect_loc_keys(node.children(), "desc", &mut descs);
+
+        let options = node
+            .children()
+            .iter()
+            .filter(|c| c.key() == "option")
+            .filter_map(|c| c.get_scalar("name"))
+            .map(|s| s.to_string())
+            .collect_vec();
+
+        let mut effects = Vec::new();
+        let mut triggers = Vec::new();
+        for child in node.children().iter().filter(|c| c.is_block()) {
+            let context = match child.key() {
+                "option" | "immediate" | "after" => ScriptContext::Effect,
+                "trigger" | "cancellation_trigger" => ScriptContext::Trigger,
+                _ => continue,
+            };
+
+            vocabulary.walk(child.children(), context, &mut |r| match r.kind {
+                ScriptReferenceKind::Effect => effects.push(r.id),
+                ScriptReferenceKind::Trigger => triggers.push(r.id),
+                ScriptReferenceKind::EventTarget | ScriptReferenceKind::Modifier => (),
+            });
+        }
+
+        EventEntry {
+            id: Self::entry_id_for_name(name),
+            name: name.to_string(),
+            namespace,
+            event_type: node.get_scalar("type").map(|s| s.to_string()),
+            hidden: node.get_scalar("hidden") == Some("yes"),
+            path: path.to_string(),
+            line: node.line,
+            titles,
+            descs,
+            options,
+            effects: effects.into_iter().unique().collect(),
+            triggers: triggers.into_iter().unique().collect(),
+            on_actions,
+            origin: None,
+        }
+    }
+
+    fn links(&self, context: &PageContext, dossier: &Dossier, ids: &[u64]) -> DocString {
+        let mut links = ids
+            .iter()
+            .filter_map(|id| dossier.entries.get(id))
+            .map(|e| (e.name().to_string(), e.id()))
+            .collect_vec();
+        links.sort();
+
+        DocString::new_from_iter(
+            links
+                .iter()
+                .map(|(name, id)| dossier.link_for_entry(context, name, id)),
+            Some(", "),
+        )
+    }
+}
+
+impl DocEntry for EventEntry {
+    fn id(&self) -> u64 {
+        self.id
+    }
+
+    fn category_id(&self) -> Option<u64> {
+        Some(Self::category_id())
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    // named for how they read on the page of the entry they point to
+    fn record_cross_references(&self, dossier: &mut Dossier) {
+        if let Some(namespace) = &self.namespace {
+            dossier.add_reference(
+                "Events In Namespace",
+                self.id,
+                EventNamespaceEntry::entry_id_for_name(namespace),
+            );
+        }
+
+        for effect in &self.effects {
+            dossier.add_reference("Used By Events", self.id, *effect);
+        }
+
+        for trigger in &self.triggers {
+            dossier.add_reference("Used By Events", self.id, *trigger);
+        }
+
+        for on_action in self.on_actions.iter().filter_map(|(_, id)| *id) {
+            dossier.add_reference("Fires Events", self.id, on_action);
+        }
+    }
+
+    fn body(&self) -> Option<DocString> {
+        None
+    }
+
+    fn origin(&self) -> Option<&Origin> {
+        self.origin.as_ref()
+    }
+
+    fn properties(&self, context: &PageContext, dossier: Rc<Dossier>) -> Vec<(String, DocString)> {
+        let mut properties = Vec::new();
+
+        if let Some(namespace) = &self.namespace {
+            properties.push((
+                "Namespace".into(),
+                dossier
+                    .link_for_entry(
+                        context,
+                        namespace,
+                        &EventNamespaceEntry::entry_id_for_name(namespace),
+                    )
+                    .into(),
+            ));
+        }
+
+        if let Some(event_type) = &self.event_type {
+            properties.push(("Type".into(), code_list(std::iter::once(event_type))));
+        }
+
+        properties.push(("Hidden".into(), self.hidden.into()));
+        properties.push((
+            "Title".into(),
+            loc_list(&self.titles, &dossier.localization),
+        ));
+        properties.push((
+            "Description".into(),
+            loc_list(&self.descs, &dossier.localization),
+        ));
+        properties.push((
+            "Options".into(),
+            loc_list(&self.options, &dossier.localization),
+        ));
+        properties.push((
+            "Triggers Used".into(),
+            self.links(context, &dossier, &self.triggers),
+        ));
+        properties.push((
+            "Effects Used".into(),
+            self.links(context, &dossier, &self.effects),
+        ));
+        properties.push((
+            "Fired By".into(),
+            DocString::new_from_iter(
+                self.on_actions.iter().map(|(name, id)| match id {
+                    Some(id) => dossier.link_for_entry(context, name, id),
+                    None => DocStringSegment::Text {
+                        contents: html_escape(name),
+                    },
+                }),
+                Some(", "),
+            ),
+        ));
+        properties.push((
+            DEFINED_IN_PROPERTY.into(),
+            code_list(std::iter::once(&format!("{}:{}", self.path, self.line))),
+        ));
+
+        properties
+    }
+}
+
+pub struct EventNamespaceEntry {
+    id: u64,
+    name: String,
+    paths: Vec<String>,
+}
+
+impl EventNamespaceEntry {
+    pub fn category_id() -> u64 {
+        util::hash(&"EVENT_NAMESPACES")
+    }
+
+    pub fn entry_id_for_name(name: &str) -> u64 {
+        util::hash(&format!("event_namespace_{}", name))
+    }
+}
+
+impl DocEntry for EventNamespaceEntry {
+    fn id(&self) -> u64 {
+        self.id
+    }
+
+    fn category_id(&self) -> Option<u64> {
+        Some(Self::category_id())
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn record_cross_references(&self, _dossier: &mut Dossier) {}
+
+    fn body(&self) -> Option<DocString> {
+        None
+    }
+
+    fn properties(
+        &self,
+        _context: &PageContext,
+        _dossier: Rc<Dossier>,
+    ) -> Vec<(String, DocString)> {
+        vec![(DEFINED_IN_PROPERTY.into(), code_list(self.paths.iter()))]
+    }
+}
+
+/// Reads events and event namespaces from the `events` directory of a game and its mods.
+pub struct EventReader;
+
+impl EventReader {
+    pub fn read(
+        files: &GameFiles,
+        dossier: &Dossier,
+        vocabulary: &ScriptVocabulary,
+    ) -> Result<(Vec<EventNamespaceEntry>, Vec<EventEntry>)> {
+        let on_action_ids: HashMap<String, u64> = dossier
+            .entries
+            .values()
+            .filter_map(|e| e.as_any().downcast_ref::<ScriptDocEntry>())
+            .filter(|e| e.category == ScriptDocCategory::OnActions)
+            .map(|e| (e.name.clone(), e.id))
+            .collect();
+
+        let mut fired_by: HashMap<String, Vec<(String, Option<u64>)>> = HashMap::new();
+        for file in files.find("common/on_actions/**/*.txt")? {
+            if file.shadowed {
+                continue;
+            }
+
+            let file = match ScriptFile::read(&file.disk_path, &file.path) {
+                Ok(file) => file,
+                Err(e) => {
+                    warn!("skipping on_actions file {}: {}", file.path, e);
+                    continue;
+                }
+            };
+            for on_action in file.nodes.iter().filter(|n| n.is_block()) {
+                let name = on_action.key().to_string();
+                for event in Self::events_fired_by(on_action) {
+                    fired_by
+                        .entry(event)
+                        .or_default()
+                        .push((name.clone(), on_action_ids.get(&name).copied()));
+                }
+            }
+        }
+
+        let mut namespaces: Vec<EventNamespaceEntry> = Vec::new();
+        let mut events: LayeredDefinitions<EventEntry> = LayeredDefinitions::new();
+
+        for game_file in files.find("events/**/*.txt")? {
+            let path = game_file.path.clone();
+            let file = match ScriptFile::read(&game_file.disk_path, &path) {
+                Ok(file) => file,
+                Err(e) => {
+                    warn!("skipping event file {}: {}", path, e);
+                    continue;
+                }
+            };
+
+            let mut namespace = None;
+            for node in &file.nodes {
+                if node.key() == "namespace" {
+                    let name = node.scalar().unwrap_or_default().to_string();
+                    match namespaces.iter_mut().find(|n| n.name == name) {
+                        _ if game_file.shadowed => (),
+                        Some(existing) => existing.paths.push(path.clone()),
+                        None => namespaces.push(EventNamespaceEntry {
+                            id: EventNamespaceEntry::entry_id_for_name(&name),
+                            name: name.clone(),
+                            paths: vec![path.clone()],
+                        }),
+                    }
+                    namespace = Some(name);
+                    continue;
+                }
+
+                if !node.is_block() {
+                    continue;
+                }
+
+                let name = node.key();
+                let event_namespace = name
+                    .split_once('.')
+                    .map(|(ns, _)| ns.to_string())
+                    .or(namespace.clone())
+                    .filter(|ns| namespaces.iter().any(|n| &n.name == ns));
+
+                events.insert_with(&game_file, name, || {
+                    EventEntry::new(
+                        name,
+                        event_namespace,
+                        &path,
+                        node,
+                        vocabulary,
+                        fired_by.get(name).cloned().unwrap_or_default(),
+                    )
+                });
+            }
+        }
+
+        let events = events
+            .into_definitions(files)
+            .into_iter()
+            .map(|(event, origin)| EventEntry { origin, ..event })
+            .collect_vec();
+
+        info!(
+            "read {} events in {} namespaces",
+            events.len(),
+            namespaces.len()
+        );
+
+        Ok((namespaces, events))
+    }
+
+    /// Returns the names of all events an on_action fires.
+    pub fn events_fired_by(on_action: &ScriptNode) -> Vec<String> {
+        let mut events = Vec::new();
+        for child in on_action.children() {
+            match child.key() {
+                "events" | "random_events" => {
+                    for event in child.children() {
+                        // random_events are weighted, like `100 = my_event.1`
+                        match event.scalar() {
+                            Some(name) if name.contains('.') => events.push(name.to_string()),
+                            _ => (),
+                        }
+                    }
+                }
+                "first_valid" | "first_valid_on_action" => {
+                    events.extend(Self::events_fired_by(child))
+                }
+                _ => (),
+            }
+        }
+
+        events
+    }
+}
+
+/// Collects localization keys used for a property like `title` or `desc`, including
+/// ones nested in `first_valid` or `triggered_desc` blocks.
+fn collect_loc_keys(nodes: &[ScriptNode], key: &str, keys: &mut Vec<String>) {
+    for node in nodes.iter().filter(|n| n.key() == key) {
+        match node.scalar() {
+            Some(value) => keys.push(value.to_string()),
+            None => collect_nested_loc_keys(node.children(), key, keys),
+        }
+    }
+}
+
+fn collect_nested_loc_keys(nodes: &[ScriptNode], key: &str, keys: &mut Vec<String>) {
+    for node in nodes {
+        match node.scalar() {
+            Some(value) if node.key() == key || node.key() == "desc" => {
+                keys.push(value.to_string())
+            }
+            Some(_) => (),
+            None if node.key() != "trigger" => collect_nested_loc_keys(node.children(), key, keys),
+            None => (),
+        }
+    }
+}
+
+/// A list of localization keys, each followed by its text if it's localized.
+fn loc_list(keys: &[String], localization: &Localization) -> DocString {
+    DocString::new_from_iter(
+        keys.iter().map(|key| DocStringSegment::Text {
+            contents: match localization.text(key) {
+                Some(text) => format!(
+                    "<code>{}</code> &ndash; {}",
+                    html_escape(key),
+                    html_escape(text)
+                ),
+                None => format!("<code>{}</code>", html_escape(key)),
+            },
+        }),
+        Some("<br/>"),
+    )
+}
@@ -1,4 +1,7 @@
-use std::{fs, path::Path};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
 
 use anyhow::{Error, Result};
 use clauser::data::script_doc_parser::ScriptDocParserResult;
@@ -27,6 +30,8 @@ pub trait GameDocProvider {
     fn read_script_docs(&self, profile: &Profile) -> Result<Option<ScriptDocParserResult>>;
     fn read_version_info(&self, profile: &Profile) -> Result<GameVersion>;
     fn get_categories(&self, profile: &Profile) -> Result<Vec<DocCategory>>;
+    /// The directory containing the game's script files, like `events` and `common`.
+    fn script_dir(&self, profile: &Profile) -> PathBuf;
 }
 
 pub fn provider_for_game(game: &ProfileGame) -> Box<impl GameDocProvider> {
@@ -7,9 +7,13 @@ use clauser::data::script_doc_parser::{
 use log::warn;
 
 use crate::{
+    concepts::ConceptEntry,
     config::{Profile, ProfileGame},
+    definitions::DefinitionKind,
     dossier::DocCategory,
     error::Error,
+    events::{EventEntry, EventNamespaceEntry},
+    modifier_types::ModifierTypeEntry,
 };
 
 use super::{BranchRevParser, GameVersion};
@@ -38,7 +42,7 @@ impl GameDocProvider for Victoria3GameDocProvider {
     }
 
     fn get_categories(&self, _profile: &Profile) -> Result<Vec<DocCategory>> {
-        Ok(vec![
+        let mut categories = vec![
             DocCategory::new(
                 &ScriptDocCategory::CustomLocalization,
                 "custom_loc",
@@ -53,6 +57,30 @@ impl GameDocProvider for Victoria3GameDocProvider {
             DocCategory::new(&ScriptDocCategory::Modifiers, "modifiers", "Modifiers"),
             DocCategory::new(&ScriptDocCategory::OnActions, "on_actions", "On Actions"),
             DocCategory::new(&ScriptDocCategory::Triggers, "triggers", "Triggers"),
-        ])
+            DocCategory::from_id(EventEntry::category_id(), "events", "Events"),
+            DocCategory::from_id(
+                EventNamespaceEntry::category_id(),
+                "event_namespaces",
+                "Event Namespaces",
+            ),
+            DocCategory::from_id(ConceptEntry::category_id(), "concepts", "Game Concepts"),
+            DocCategory::from_id(
+                ModifierTypeEntry::category_id(),
+                "modifier_types",
+                "Modifier Types",
+            ),
+        ];
+
+        categories.extend(DefinitionKind::ALL.map(|k| k.category()));
+        Ok(categories)
+    }
+
+    fn script_dir(&self, profile: &Profile) -> PathBuf {
+        // the install directory keeps all of the script files in `game`
+        let path = PathBuf::from(&profile.game_data_dir).join("game");
+        match path.is_dir() {
+            true => path,
+            false => PathBuf::from(&profile.game_data_dir),
+        }
     }
 }
@@ -1,9 +1,10 @@
 use std::{
     cell::RefCell,
-    collections::{hash_map::Entry, HashMap},
+    collections::{hash_map::Entry, BTreeMap, HashMap, HashSet},
     fs,
     path::{Path, PathBuf},
     rc::Rc,
+    sync::{Arc, Mutex},
 };
 
 use anyhow::Result;
@@ -18,11 +19,11 @@ use crate::{
     dossier::{DocInfo, Dossier},
     helpers::{
         AssetHelper, BreadcrumbsHelper, ColumnsHelper, PageUrlHelper, PaginationHelper,
-        SiteMapHelper,
+        SiteMapHelper, TranslateHelper,
     },
-    mapper::{SiteMap, SiteMapper},
-    page::{Breadcrumbs, Page, PageContext},
-    theme::{Template, Theme},
+    mapper::{SiteMap, SiteMapper, VersionLink},
+    page::{Breadcrumbs, LandingPage, Page, PageContext},
+    theme::{Strings, Template, Theme},
     util,
 };
 
@@ -33,8 +34,15 @@ pub struct SiteProfile {
 }
 
 impl SiteProfile {
-    pub fn new(config: &Config, profile: Profile, dossier: Rc<Dossier>) -> SiteProfile {
-        let pages = Dossier::create_pages(dossier.clone(), config);
+    pub fn new(
+        config: &Config,
+        profile: Profile,
+        dossier: Rc<Dossier>,
+        extra_pages: Vec<Box<dyn Page>>,
+    ) -> SiteProfile {
+        let mut pages = Dossier::create_pages(dossier.clone(), config);
+        pages.extend(extra_pages);
+        pages.push(Box::new(LandingPage::new(dossier.clone(), &pages)));
 
         SiteProfile {
             profile,
@@ -44,6 +52,25 @@ impl SiteProfile {
     }
 }
 
+/// The data every page's template is rendered with.
+#[derive(Serialize)]
+struct PageData<'a> {
+    /// The title of the page, followed by the title of its profile.
+    title: String,
+    /// The title of the page on its own.
+    name: String,
+    /// The data specific to the page's template.
+    data: Value,
+    breadcrumbs: Breadcrumbs,
+    page_id: u64,
+    site_map: SiteMap,
+    doc_info: DocInfo,
+    /// The other versions of the site this page can be found in.
+    versions: Vec<VersionLink>,
+    /// The theme's settings.
+    theme: &'a BTreeMap<String, Value>,
+}
+
 pub struct SiteGenerator<'config> {
     profiles: Vec<SiteProfile>,
     pub mapper: Rc<RefCell<SiteMapper>>,
@@ -59,25 +86,156 @@ impl<'config> SiteGenerator<'config> {
         }
     }
 
-    pub fn add_profile(&mut self, profile: Profile, dossier: Rc<Dossier>) {
-        let profile = SiteProfile::new(self.config, profile, dossier);
+    /// Adds a profile to the site, along with any pages that aren't created from its dossier.
+    pub fn add_profile(
+        &mut self,
+        profile: Profile,
+        dossier: Rc<Dossier>,
+        extra_pages: Vec<Box<dyn Page>>,
+    ) {
+        let profile = SiteProfile::new(self.config, profile, dossier, extra_pages);
         self.mapper.borrow_mut().record_profile(&profile);
         self.profiles.push(profile)
     }
 
+    /// Renders the index at the root of the output directory, which links to every profile.
+    fn render_root<'t>(
+        &self,
+        handlebars: &Handlebars,
+        theme: &'t dyn Theme<'t>,
+        template: &Template,
+    ) -> Result<()> {
+        #[derive(Serialize)]
+        struct RootProfile<'a> {
+            title: &'a str,
+            dir: String,
+            url: String,
+            doc_info: &'a DocInfo,
+        }
+
+        #[derive(Serialize)]
+        struct RootData<'a> {
+            page_id: u64,
+            profiles: Vec<RootProfile<'a>>,
+            theme: &'a BTreeMap<String, Value>,
+        }
+
+        let mapper = self.mapper.borrow();
+        let data = RootData {
+            page_id: SiteMapper::root_page_id(),
+            profiles: self
+                .profiles
+                .iter()
+                .map(|p| {
+                    let dir = mapper.profile_dir(&p.profile);
+                    RootProfile {
+                        title: &p.profile.title,
+                        url: format!("{}/index.html", dir),
+                        dir,
+                        doc_info: &p.dossier.info,
+                    }
+                })
+                .collect(),
+            theme: theme.settings(),
+        };
+
+        let rendered = handlebars.render(theme.resolve_template(template)?, &data)?;
+        let path = &mapper.page_paths[&SiteMapper::root_page_id()];
+        fs::write(&path.disk, html_minifier::minify(rendered).unwrap())?;
+
+        info!("rendered root index to {}", path.disk.display());
+        Ok(())
+    }
+
+    /// Builds the data a page's template is rendered with.
+    fn page_data<'t>(
+        &self,
+        profile: &SiteProfile,
+        page: &dyn Page,
+        context: &PageContext,
+        site_map: &SiteMap,
+        dir: &Path,
+        theme: &'t dyn Theme<'t>,
+    ) -> PageData<'t> {
+        let info = page.info();
+        PageData {
+            title: format!("{} | {}", &info.title, &profile.profile.title),
+            name: info.title.clone(),
+            page_id: self.mapper.borrow().scoped(page.id()),
+            data: page.data(context),
+            breadcrumbs: Breadcrumbs::from_page(page, profile, dir),
+            site_map: site_map.clone(),
+            doc_info: profile.dossier.info.clone(),
+            versions: self.mapper.borrow().version_links(page),
+            theme: theme.settings(),
+        }
+    }
+
+    /// Builds the data for the first page rendered with each template, by the template's name
+    /// without any specialisation, as a sample for theme authors.
+    pub fn sample_data<'t>(&self, theme: &'t dyn Theme<'t>) -> Result<BTreeMap<String, Value>> {
+        let context = PageContext::new(self.mapper.clone());
+        let mut samples = BTreeMap::new();
+
+        for p in &self.profiles {
+            self.mapper.borrow_mut().set_current_profile(&p.profile);
+            let site_map = SiteMap::from_pages(&p, &self.mapper.borrow());
+            let dir = PathBuf::from(self.mapper.borrow().profile_dir(&p.profile));
+
+            for page in &p.pages {
+                let template = page.info().template;
+                let name = template.fallbacks().last().unwrap().to_string();
+                if !samples.contains_key(&name) {
+                    let data = self.page_data(p, page.as_ref(), &context, &site_map, &dir, theme);
+                    samples.insert(name, serde_json::to_value(data)?);
+                }
+            }
+        }
+
+        Ok(samples)
+    }
+
     pub fn generate<'t>(&self, theme: &'t dyn Theme<'t>) -> Result<()> {
+        let has_root = self.mapper.borrow().uses_profile_dirs();
+        if has_root {
+            self.mapper.borrow_mut().record_root();
+        }
+
         let mapping: HashMap<u64, String> = self.mapper.borrow().page_path_mapping();
 
+        // assets that templates link to are also written under names that change with their
+        // contents, so they can be cached
+        let assets = theme
+            .assets()
+            .iter()
+            .map(|(path, bytes)| {
+                let path = path.replace("\\", "/");
+                let fingerprinted = Self::fingerprint(&path, bytes);
+                (path, fingerprinted, bytes)
+            })
+            .collect_vec();
+
         let mut handlebars = Handlebars::new();
 
         for (name, str) in theme.partials() {
             handlebars.register_partial(name, str)?;
         }
 
+        let used_assets = Arc::new(Mutex::new(HashSet::new()));
         handlebars.register_helper(
             "asset_url",
             Box::new(AssetHelper {
                 mapper: mapping.clone(),
+                assets: assets
+                    .iter()
+                    .filter_map(|(path, fingerprinted, _)| {
+                        Some((
+                            path.strip_prefix("assets/")?.to_string(),
+                            fingerprinted.strip_prefix("assets/")?.to_string(),
+                        ))
+                    })
+                    .collect(),
+                used: used_assets.clone(),
             }),
         );
         handlebars.register_helper(
@@ -98,57 +256,87 @@ impl<'config> SiteGenerator<'config> {
         handlebars.register_helper("pagination", Box::new(PaginationHelper));
         handlebars.register_helper("columns", Box::new(ColumnsHelper));
 
+        let strings = Strings::new(theme, self.config)?;
+        handlebars.register_helper("t", Box::new(TranslateHelper { strings }));
+
         handlebars_misc_helpers::register(&mut handlebars);
 
-        let templates: Vec<Template> = self
+        // registered last, so themes can replace any of the built in helpers
+        for (name, script) in theme.helpers() {
+            handlebars
+                .register_script_helper(name, &script.source)
+                .map_err(|e| {
+                    anyhow::Error::msg(format!(
+                        "Can't compile helper {} in {}: {}",
+                        name,
+                        script.path.display(),
+                        e
+                    ))
+                })?;
+        }
+
+        let templates = self
             .profiles
             .iter()
             .flat_map(|p| p.pages.iter())
-            .map(|p| p.info().template)
-            .unique()
-            .collect();
+            .map(|p| p.info())
+            .into_group_map_by(|info| info.template.clone());
 
-        // make sure all templates are compiled before going through each page
-        for template in templates {
-            handlebars
-                .register_template_string(template.into(), theme.str_for_template(template)?)?;
+        // make sure every page has a template and they're all compiled before rendering any page
+        let mut missing = vec![];
+        for (template, pages) in templates.iter().sorted_by_key(|(t, _)| t.name()) {
+            match theme.resolve_template(template) {
+                Ok(name) if handlebars.get_template(name).is_none() => handlebars
+                    .register_template_string(name, theme.str_for_template(name).unwrap())?,
+                Ok(_) => {}
+                Err(_) => missing.push(format!(
+                    "{} (needed by {} pages, like \"{}\")",
+                    template.name(),
+                    pages.len(),
+                    pages[0].title
+                )),
+            }
         }
 
-        #[derive(Serialize)]
-        struct PageData {
-            title: String,
-            name: String,
-            data: Value,
-            breadcrumbs: Breadcrumbs,
-            page_id: u64,
-            site_map: SiteMap,
-            doc_info: DocInfo,
+        let root_template = Template::new("root");
+        if has_root {
+            match theme.resolve_template(&root_template) {
+                Ok(name) => handlebars
+                    .register_template_string(name, theme.str_for_template(name).unwrap())?,
+                Err(_) => missing.push("root (needed by the index listing every profile)".into()),
+            }
+        }
+
+        if !missing.is_empty() {
+            return Err(anyhow::Error::msg(format!(
+                "The theme is missing templates: {}",
+                missing.join(", ")
+            )));
         }
 
         let context = PageContext::new(self.mapper.clone());
 
         for p in &self.profiles {
-            let site_map = SiteMap::from_pages(&p);
+            self.mapper.borrow_mut().set_current_profile(&p.profile);
+            let site_map = SiteMap::from_pages(&p, &self.mapper.borrow());
+            let dir = PathBuf::from(self.mapper.borrow().profile_dir(&p.profile));
 
             for page in &p.pages {
                 let info = page.info();
-                let title = format!("{} | {}", &info.title, &p.profile.title);
-                let name = info.title.clone();
-                let data = PageData {
-                    title,
-                    name,
-                    page_id: page.id(),
-                    data: page.data(&context),
-                    breadcrumbs: Breadcrumbs::from_page(page.as_ref(), p),
-                    site_map: site_map.clone(),
-                    doc_info: p.dossier.info.clone(),
-                };
-
-                let rendered = handlebars.render(info.template.into(), &data)?;
+                let page_id = self.mapper.borrow().scoped(page.id());
+                let data = self.page_data(p, page.as_ref(), &context, &site_map, &dir, theme);
+
+                let template = theme.resolve_template(&info.template)?;
+                let rendered = handlebars.render(template, &data).map_err(|e| {
+                    anyhow::Error::msg(format!(
+                        "Failed to render {} with template {}: {}",
+                        info.title, template, e
+                    ))
+                })?;
                 let minified = html_minifier::minify(rendered).unwrap();
 
                 let mapper = self.mapper.borrow();
-                let path = mapper.page_paths.get(&page.id()).unwrap();
+                let path = mapper.page_paths.get(&page_id).unwrap();
                 if let Some(dir) = path.disk.parent() {
                     fs::create_dir_all(dir)?;
                 }
@@ -162,23 +350,56 @@ impl<'config> SiteGenerator<'config> {
             }
         }
 
+        if has_root {
+            self.render_root(&handlebars, theme, &root_template)?;
+        }
+
         let assets_dir = PathBuf::from(&self.config.output_dir).join("assets");
         if !assets_dir.is_dir() {
             fs::create_dir(&assets_dir)?;
         }
 
-        // write theme assets
-        for (path, bytes) in theme.assets() {
-            let out_path = PathBuf::from(&self.config.output_dir).join(path);
-            fs::write(&out_path, bytes)?;
-            info!(
-                "wrote asset {}",
-                out_path.to_str().unwrap().replace("\\", "/")
-            );
+        // write theme assets where they are in the theme, so stylesheets and templates can refer
+        // to each other by relative paths, and under their fingerprinted names if linked to
+        let used_assets = used_assets.lock().unwrap();
+        for (path, fingerprinted, bytes) in &assets {
+            let used = path
+                .strip_prefix("assets/")
+                .is_some_and(|name| used_assets.contains(name));
+            let out_paths = match used {
+                true => vec![path, fingerprinted],
+                false => vec![path],
+            };
+
+            for out_path in out_paths {
+                let out_path = PathBuf::from(&self.config.output_dir).join(out_path);
+                if let Some(dir) = out_path.parent() {
+                    fs::create_dir_all(dir)?;
+                }
+                fs::write(&out_path, bytes)?;
+                info!(
+                    "wrote asset {}",
+                    out_path.to_str().unwrap().replace("\\", "/")
+                );
+            }
         }
 
         info!("generated to {}", self.config.output_dir.to_str().unwrap());
 
         Ok(())
     }
+
+    /// Adds a hash of an asset's contents to its file name, so `assets/style.css` becomes
+    /// `assets/style.3fa2c1d8.css`.
+    fn fingerprint(path: &str, bytes: &[u8]) -> String {
+        let hash = format!("{:08x}", util::hash(&bytes) as u32);
+        let path = PathBuf::from(path);
+        let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+        let name = match path.extension().and_then(|e| e.to_str()) {
+            Some(extension) => format!("{}.{}.{}", stem, hash, extension),
+            None => format!("{}.{}", stem, hash),
+        };
+
+        path.with_file_name(name).to_str().unwrap().to_string()
+    }
 }
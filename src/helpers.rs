@@ -1,12 +1,12 @@
 use std::{
     cell::RefCell,
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     sync::{Arc, Mutex},
 };
 
 use handlebars::{
-    handlebars_helper, BlockParams, Context, Handlebars, Helper, HelperDef, HelperResult, Output,
-    PathAndJson, RenderContext, RenderErrorReason, Renderable, Template,
+    handlebars_helper, html_escape, BlockParams, Context, Handlebars, Helper, HelperDef,
+    HelperResult, Output, PathAndJson, RenderContext, RenderErrorReason, Renderable, Template,
 };
 use itertools::Itertools;
 use log::{info, trace};
@@ -16,6 +16,7 @@ use serde_json::Value;
 use crate::{
     mapper::{SiteMap, SiteMapper},
     page::{Breadcrumb, Breadcrumbs},
+    theme::Strings,
 };
 
 use handlebars::BlockContext;
@@ -36,6 +37,11 @@ pub(crate) fn create_block<'rc>(param: &PathAndJson<'rc>) -> BlockContext<'rc> {
 #[derive(Clone)]
 pub struct AssetHelper {
     pub mapper: HashMap<u64, String>,
+    /// Maps the name of each asset to the fingerprinted name it's written under.
+    pub assets: HashMap<String, String>,
+    /// The names of the assets that have been linked to, which are the only ones that need writing
+    /// under their fingerprinted names.
+    pub used: Arc<Mutex<HashSet<String>>>,
 }
 
 impl HelperDef for AssetHelper {
@@ -62,16 +68,58 @@ impl HelperDef for AssetHelper {
             .and_then(|v| v.as_u64())
             .unwrap();
 
+        let fingerprinted = self.assets.get(asset).ok_or_else(|| {
+            RenderErrorReason::Other(format!("Theme doesn't have an asset named {}", asset))
+        })?;
+        self.used.lock().unwrap().insert(asset.to_string());
+
         out.write(&SiteMapper::asset_url_with_mapping(
             &self.mapper,
             page_id,
-            asset,
+            fingerprinted,
         ))?;
 
-        // add random number afterwards to break caching
-        let rand = rand::random::<u64>();
-        out.write("?")?;
-        out.write(&rand.to_string())?;
+        Ok(())
+    }
+}
+
+/// Looks up a string in the theme's string table, like `{{t "page_of" current=1 total=3}}`. Each
+/// `{name}` in the string is replaced with the HTML-escaped hash parameter of that name.
+#[derive(Clone)]
+pub struct TranslateHelper {
+    pub strings: Strings,
+}
+
+impl HelperDef for TranslateHelper {
+    fn call<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper,
+        _hb: &Handlebars,
+        _context: &Context,
+        _rc: &mut RenderContext,
+        out: &mut dyn Output,
+    ) -> HelperResult {
+        let key = h.param(0).and_then(|v| v.value().as_str()).ok_or(
+            RenderErrorReason::ParamTypeMismatchForName("t", "0".to_string(), "&str".to_string()),
+        )?;
+
+        let mut text = self
+            .strings
+            .get(key)
+            .ok_or_else(|| {
+                RenderErrorReason::Other(format!("Theme doesn't have a string named {}", key))
+            })?
+            .to_string();
+
+        for (name, value) in h.hash() {
+            let value = match value.value() {
+                Value::String(s) => s.clone(),
+                v => v.to_string(),
+            };
+            text = text.replace(&format!("{{{}}}", name), &html_escape(&value));
+        }
+
+        out.write(&text)?;
 
         Ok(())
     }
@@ -433,7 +481,10 @@ impl<'reg: 'rc, 'rc> SiteMapHelper {
         rc.pop_block();
 
         let mut children = sitemap.children.values().collect_vec();
-        children.sort_by_key(|c| c.borrow().title.clone());
+        children.sort_by_key(|c| {
+            let c = c.borrow();
+            (c.title.clone(), c.key.clone())
+        });
         for m in children {
             self.recurse_sitemap(
                 param,
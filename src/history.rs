@@ -0,0 +1,152 @@
+use std::{collections::HashMap, rc::Rc};
+
+use anyhow::Result;
+use handlebars::html_escape;
+use itertools::Itertools;
+use log::info;
+
+use crate::{
+    diff::{ChangeKind, DossierDiff},
+    dossier::Dossier,
+    record::EntryRecord,
+    snapshot::DossierSnapshot,
+};
+
+/// The versions of the game in which an entry was introduced and changed.
+pub struct EntryHistory {
+    /// The first version the entry appears in. If the entry appears in the oldest snapshot, this is
+    /// that snapshot's version, even though the entry might be older.
+    pub introduced_in: String,
+    /// The versions in which the entry's description or properties changed, oldest first.
+    pub changed_in: Vec<String>,
+}
+
+impl EntryHistory {
+    /// A badge to show next to an entry's name, as HTML.
+    pub fn badge(&self, dossier: &Dossier) -> String {
+        format!(
+            "<span class=\"pd-history\">{}</span>",
+            html_escape(&dossier.string("since", &[("version", &self.introduced_in)]))
+        )
+    }
+}
+
+/// An entry that used to exist, but has since been removed from the game.
+pub struct RemovedEntry {
+    /// The entry as it was in the last version it appeared in.
+    pub record: EntryRecord,
+    pub introduced_in: String,
+    pub removed_in: String,
+}
+
+/// Everything we know about how entries have changed across versions of the game.
+#[derive(Default)]
+pub struct HistoryIndex {
+    entries: HashMap<u64, EntryHistory>,
+    removed: Vec<RemovedEntry>,
+}
+
+impl HistoryIndex {
+    /// Builds the history of a dossier's entries from snapshots of older versions, oldest first.
+    pub fn new(snapshots: Vec<DossierSnapshot>, dossier: Rc<Dossier>) -> Result<HistoryIndex> {
+        let mut versions = snapshots
+            .into_iter()
+            .map(|s| (s.info.game_version().to_string(), s.entries))
+            .collect_vec();
+        versions.push((
+            dossier.info.game_version().to_string(),
+            EntryRecord::record_all(dossier.clone())?,
+        ));
+
+        let key = |r: &EntryRecord| (r.category.clone(), r.name.clone());
+        let mut histories: HashMap<(String, String), EntryHistory> = HashMap::new();
+        let mut removed: HashMap<(String, String), RemovedEntry> = HashMap::new();
+
+        let (first_version, first_records) = &versions[0];
+        for record in first_records {
+            histories.insert(
+                key(record),
+                EntryHistory {
+                    introduced_in: first_version.clone(),
+                    changed_in: vec![],
+                },
+            );
+        }
+
+        for ((old_version, old), (new_version, new)) in versions.iter().tuple_windows() {
+            let diff = DossierDiff::new(old, new, old_version, new_version);
+            for change in &diff.changes {
+                let change_key = (change.category.clone(), change.name.clone());
+                match change.kind {
+                    ChangeKind::Added => {
+                        removed.remove(&change_key);
+                        histories.insert(
+                            change_key,
+                            EntryHistory {
+                                introduced_in: new_version.clone(),
+                                changed_in: vec![],
+                            },
+                        );
+                    }
+                    ChangeKind::Changed => {
+                        if let Some(history) = histories.get_mut(&change_key) {
+                            history.changed_in.push(new_version.clone());
+                        }
+                    }
+                    ChangeKind::Removed => {
+                        let history = histories.remove(&change_key);
+                        let record = old.iter().find(|r| key(r) == change_key).unwrap();
+                        removed.insert(
+                            change_key,
+                            RemovedEntry {
+                                record: record.clone(),
+                                introduced_in: history
+                                    .map(|h| h.introduced_in)
+                                    .unwrap_or_else(|| old_version.clone()),
+                                removed_in: new_version.clone(),
+                            },
+                        );
+                    }
+                }
+            }
+        }
+
+        let (_, current) = versions.last().unwrap();
+        let entries = current
+            .iter()
+            .filter_map(|r| histories.remove(&key(r)).map(|h| (r.id, h)))
+            .collect();
+
+        let mut removed = removed.into_values().collect_vec();
+        removed.sort_by(|a, b| {
+            a.record
+                .category
+                .cmp(&b.record.category)
+                .then(a.record.name.cmp(&b.record.name))
+        });
+
+        info!(
+            "tracked history across {} versions, {} entries removed",
+            versions.len(),
+            removed.len()
+        );
+
+        Ok(HistoryIndex { entries, removed })
+    }
+
+    pub fn get(&self, id: u64) -> Option<&EntryHistory> {
+        self.entries.get(&id)
+    }
+
+    /// Returns every removed entry, sorted by category and name.
+    pub fn removed(&self) -> &[RemovedEntry] {
+        &self.removed
+    }
+
+    /// Returns the entries removed from a category, by the category's name.
+    pub fn removed_in<'a>(&'a self, category: &'a str) -> impl Iterator<Item = &'a RemovedEntry> {
+        self.removed
+            .iter()
+            .filter(move |r| r.record.category == category)
+    }
+}
@@ -0,0 +1,588 @@
+use std::{collections::HashMap, fmt::Display};
+
+use anyhow::Result;
+use clauser::data::script_doc_parser::{ScriptDocContent, ScriptDocEntry};
+use itertools::Itertools;
+use log::{info, warn};
+
+use crate::{
+    dossier::Dossier,
+    events::EventReader,
+    mods::{GameFile, GameFiles},
+    script::{ScriptContext, ScriptFile, ScriptNode, ScriptReferenceKind, ScriptVocabulary},
+};
+
+/// Iterators change the scope to their targets for the contents of their block.
+const ITERATOR_PREFIXES: [&str; 4] = ["any_", "every_", "random_", "ordered_"];
+
+/// Directories whose top-level blocks are always evaluated in a known scope.
+const DIRECTORY_SCOPES: [(&str, &str); 2] = [
+    ("common/decisions/", "country"),
+    ("common/journal_entries/", "country"),
+];
+
+/// Blocks whose contents are more script, rather than the arguments of an effect or trigger.
+const CONTROL_BLOCKS: [&str; 16] = [
+    "if",
+    "else_if",
+    "else",
+    "while",
+    "random",
+    "random_list",
+    "hidden_effect",
+    "custom_tooltip",
+    "custom_description",
+    "show_as_tooltip",
+    "AND",
+    "OR",
+    "NOT",
+    "NOR",
+    "NAND",
+    "trigger_if",
+];
+
+/// Keys used as arguments to iterators, control blocks and event options, which aren't effects or
+/// triggers themselves.
+const ARGUMENT_KEYS: [&str; 18] = [
+    "count",
+    "percent",
+    "max",
+    "min",
+    "order_by",
+    "position",
+    "check_range_bounds",
+    "weight",
+    "modifier",
+    "text",
+    "desc",
+    "value",
+    "tooltip",
+    "include_self",
+    "name",
+    "default_option",
+    "highlighted_option",
+    "ai_chance",
+];
+
+/// Directories whose top-level blocks are lists of modifiers.
+const MODIFIER_DIRECTORIES: [&str; 2] = ["common/static_modifiers/", "common/modifiers/"];
+
+/// Keys that make a `modifier` block a weight modifier, like the ones in `ai_chance`, rather than
+/// a list of modifiers.
+const WEIGHT_MODIFIER_KEYS: [&str; 3] = ["factor", "add", "multiply"];
+
+/// A problem found in a script file.
+pub struct Diagnostic {
+    pub path: String,
+    pub line: usize,
+    pub message: String,
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}: {}", self.path, self.line, self.message)
+    }
+}
+
+/// The scopes an effect or trigger supports, and the scopes it iterates over if it's an iterator.
+struct KeywordScopes {
+    supported: Vec<String>,
+    targets: Vec<String>,
+}
+
+impl KeywordScopes {
+    /// Whether this keyword can be used in any of the given scopes. Keywords that don't list their
+    /// scopes, or that work anywhere, are always supported.
+    fn supports(&self, scopes: &[String]) -> bool {
+        self.supported.is_empty()
+            || self.supported.iter().any(|s| s == "none" || s == "all")
+            || scopes.iter().any(|s| self.supported.contains(s))
+    }
+}
+
+/// The scopes that are known at some point in a script file. `None` means the scope isn't known,
+/// so nothing is checked against it.
+type Scopes = Option<Vec<String>>;
+
+#[derive(Clone)]
+struct ScopeState {
+    current: Scopes,
+    root: Scopes,
+    prev: Scopes,
+}
+
+impl ScopeState {
+    fn rooted(root: Scopes) -> ScopeState {
+        ScopeState {
+            current: root.clone(),
+            root,
+            prev: None,
+        }
+    }
+
+    fn with_current(&self, current: Scopes) -> ScopeState {
+        ScopeState {
+            current,
+            root: self.root.clone(),
+            prev: self.current.clone(),
+        }
+    }
+}
+
+/// Reads every script file the profile's mods add or override.
+fn read_mod_scripts(files: &GameFiles) -> Result<Vec<ScriptFile>> {
+    let files: Vec<GameFile> = files
+        .find("{common,events}/**/*.txt")?
+        .into_iter()
+        .filter(|f| f.layer > 0 && !f.shadowed)
+        .collect();
+
+    let mut scripts = Vec::new();
+    for file in files {
+        match ScriptFile::read(&file.disk_path, &file.path) {
+            Ok(script) => scripts.push(script),
+            Err(e) => warn!("skipping script file {}: {}", file.path, e),
+        }
+    }
+
+    Ok(scripts)
+}
+
+fn sort_diagnostics(diagnostics: &mut [Diagnostic]) {
+    diagnostics.sort_by(|a, b| a.path.cmp(&b.path).then(a.line.cmp(&b.line)));
+}
+
+/// Checks that effects and triggers in a mod's script files are only used in scopes they support,
+/// following the current scope through event target links and iterators.
+pub struct ScopeChecker<'d> {
+    dossier: &'d Dossier,
+    effects: HashMap<String, KeywordScopes>,
+    triggers: HashMap<String, KeywordScopes>,
+    /// The output scopes of every event target.
+    links: HashMap<String, Vec<String>>,
+    on_actions: HashMap<String, String>,
+}
+
+impl<'d> ScopeChecker<'d> {
+    pub fn new(dossier: &'d Dossier) -> ScopeChecker<'d> {
+        let mut checker = ScopeChecker {
+            dossier,
+            effects: HashMap::new(),
+            triggers: HashMap::new(),
+            links: HashMap::new(),
+            on_actions: HashMap::new(),
+        };
+
+        for entry in dossier.entries.values() {
+            let Some(entry) = entry.as_any().downcast_ref::<ScriptDocEntry>() else {
+                continue;
+            };
+
+            match &entry.content {
+                Some(ScriptDocContent::Effects {
+                    supported_scopes,
+                    supported_targets,
+                    ..
+                }) => {
+                    let scopes = checker.keyword_scopes(supported_scopes, supported_targets);
+                    checker.effects.insert(entry.name.clone(), scopes);
+                }
+                Some(ScriptDocContent::Triggers {
+                    supported_scopes,
+                    supported_targets,
+                    ..
+                }) => {
+                    let scopes = checker.keyword_scopes(supported_scopes, supported_targets);
+                    checker.triggers.insert(entry.name.clone(), scopes);
+                }
+                Some(ScriptDocContent::EventTargets { output_scopes, .. }) => {
+                    let scopes = checker.scope_names(output_scopes);
+                    checker.links.insert(entry.name.clone(), scopes);
+                }
+                Some(ScriptDocContent::OnActions { expected_scope, .. }) => {
+                    if let Some(scope) = checker.scope_names(&[*expected_scope]).pop() {
+                        checker.on_actions.insert(entry.name.clone(), scope);
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        checker
+    }
+
+    fn scope_names(&self, scopes: &[usize]) -> Vec<String> {
+        scopes
+            .iter()
+            .filter_map(|s| self.dossier.string_table.get(*s))
+            .map(|s| s.to_string())
+            .collect()
+    }
+
+    fn keyword_scopes(&self, supported: &[usize], targets: &[usize]) -> KeywordScopes {
+        KeywordScopes {
+            supported: self.scope_names(supported),
+            targets: self.scope_names(targets),
+        }
+    }
+
+    /// Checks every script file the profile's mods add or override.
+    pub fn check(&self, files: &GameFiles) -> Result<Vec<Diagnostic>> {
+        let scripts = read_mod_scripts(files)?;
+        info!("checking scopes in {} script files", scripts.len());
+
+        let event_scopes = self.event_scopes(&scripts);
+
+        let mut diagnostics = Vec::new();
+        for script in &scripts {
+            let context = ScriptVocabulary::file_context(&script.path);
+
+            let directory_scope = DIRECTORY_SCOPES
+                .iter()
+                .find(|(dir, _)| script.path.starts_with(dir))
+                .map(|(_, scope)| vec![scope.to_string()]);
+
+            for node in script.nodes.iter().filter(|n| n.is_block()) {
+                let root = if script.path.starts_with("events/") {
+                    node.get_scalar("scope")
+                        .map(|s| vec![s.to_string()])
+                        .or_else(|| event_scopes.get(node.key()).cloned())
+                } else if script.path.starts_with("common/on_actions/") {
+                    self.on_actions.get(node.key()).map(|s| vec![s.clone()])
+                } else {
+                    directory_scope.clone()
+                };
+
+                self.walk(
+                    script,
+                    node.children(),
+                    context,
+                    &ScopeState::rooted(root),
+                    &mut diagnostics,
+                );
+            }
+        }
+
+        sort_diagnostics(&mut diagnostics);
+        Ok(diagnostics)
+    }
+
+    /// Works out the scope of events from the on_actions that fire them.
+    fn event_scopes(&self, scripts: &[ScriptFile]) -> HashMap<String, Vec<String>> {
+        let mut scopes: HashMap<String, Vec<String>> = HashMap::new();
+        for script in scripts
+            .iter()
+            .filter(|s| s.path.starts_with("common/on_actions/"))
+        {
+            for on_action in &script.nodes {
+                let Some(scope) = self.on_actions.get(on_action.key()) else {
+                    continue;
+                };
+
+                for event in EventReader::events_fired_by(on_action) {
+                    let event_scopes = scopes.entry(event).or_default();
+                    if !event_scopes.contains(scope) {
+                        event_scopes.push(scope.clone());
+                    }
+                }
+            }
+        }
+
+        scopes
+    }
+
+    fn walk(
+        &self,
+        script: &ScriptFile,
+        nodes: &[ScriptNode],
+        context: Option<ScriptContext>,
+        state: &ScopeState,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) {
+        for node in nodes {
+            let key = node.key();
+            let keyword = match context {
+                Some(ScriptContext::Effect) => self.effects.get(key).map(|k| ("effect", k)),
+                Some(ScriptContext::Trigger) => self.triggers.get(key).map(|k| ("trigger", k)),
+                None => None,
+            };
+
+            if let (Some((kind, keyword)), Some(current)) = (keyword, &state.current) {
+                if !keyword.supports(current) {
+                    diagnostics.push(Diagnostic {
+                        path: script.path.clone(),
+                        line: node.line,
+                        message: format!(
+                            "{} `{}` used in {} scope, but it only supports {}",
+                            kind,
+                            key,
+                            current.join("/"),
+                            keyword.supported.join(", ")
+                        ),
+                    });
+                }
+            }
+
+            if !node.is_block() {
+                continue;
+            }
+
+            let inner = match keyword {
+                Some((_, keyword))
+                    if ITERATOR_PREFIXES.iter().any(|p| key.starts_with(p))
+                        && !keyword.targets.is_empty() =>
+                {
+                    state.with_current(Some(keyword.targets.clone()))
+                }
+                Some(_) => state.clone(),
+                None => match self.follow_links(key, state) {
+                    Some(scopes) => state.with_current(scopes),
+                    None => state.clone(),
+                },
+            };
+
+            let context = ScriptVocabulary::block_context(key).or(context);
+            self.walk(script, node.children(), context, &inner, diagnostics);
+        }
+    }
+
+    /// Follows a chain of links like `owner.capital`, returning the scope it ends up in.
+    /// Returns None if the key isn't a link at all, so the scope doesn't change.
+    fn follow_links(&self, key: &str, state: &ScopeState) -> Option<Scopes> {
+        let mut scopes = state.current.clone();
+        let mut prev = state.prev.clone();
+        for (i, link) in key.split('.').enumerate() {
+            let next = match link {
+                "this" => scopes.clone(),
+                "root" => state.root.clone(),
+                "prev" => prev.clone(),
+                // saved scopes and variables could be anything
+                _ if link.contains(':') => None,
+                _ => match self.links.get(link) {
+                    Some(outputs) if outputs.is_empty() || outputs.iter().any(|o| o == "none") => {
+                        None
+                    }
+                    Some(outputs) => Some(outputs.clone()),
+                    None if i == 0 => return None,
+                    None => None,
+                },
+            };
+
+            prev = scopes;
+            scopes = next;
+        }
+
+        Some(scopes)
+    }
+}
+
+/// Finds effects, triggers, event targets and modifiers in a mod's script files that the dossier
+/// doesn't know about, suggesting similarly named ones.
+pub struct IdentifierChecker<'v> {
+    vocabulary: &'v ScriptVocabulary,
+}
+
+impl<'v> IdentifierChecker<'v> {
+    pub fn new(vocabulary: &'v ScriptVocabulary) -> IdentifierChecker<'v> {
+        IdentifierChecker { vocabulary }
+    }
+
+    /// Checks every script file the profile's mods add or override.
+    pub fn check(&self, files: &GameFiles) -> Result<Vec<Diagnostic>> {
+        let scripts = read_mod_scripts(files)?;
+        info!("checking identifiers in {} script files", scripts.len());
+
+        let mut diagnostics = Vec::new();
+        for script in &scripts {
+            for node in script.nodes.iter().filter(|n| n.is_block()) {
+                if MODIFIER_DIRECTORIES
+                    .iter()
+                    .any(|d| script.path.starts_with(d))
+                {
+                    self.check_modifiers(script, node.children(), &mut diagnostics);
+                    continue;
+                }
+
+                let context = ScriptVocabulary::file_context(&script.path);
+                self.walk(script, node.children(), context, &mut diagnostics);
+            }
+        }
+
+        sort_diagnostics(&mut diagnostics);
+        Ok(diagnostics)
+    }
+
+    fn check_modifiers(
+        &self,
+        script: &ScriptFile,
+        nodes: &[ScriptNode],
+        diagnostics: &mut Vec<Diagnostic>,
+    ) {
+        for node in nodes {
+            let key = node.key();
+            if key == "icon" || Self::is_dynamic(key) || self.vocabulary.modifier(key).is_some() {
+                continue;
+            }
+
+            diagnostics.push(self.unknown(script, node, key, ScriptReferenceKind::Modifier));
+        }
+    }
+
+    fn walk(
+        &self,
+        script: &ScriptFile,
+        nodes: &[ScriptNode],
+        context: Option<ScriptContext>,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) {
+        for node in nodes {
+            let key = node.key();
+
+            // modifier blocks can be anywhere, like the effects of a law or a technology
+            if key == "modifier" && node.is_block() && !Self::is_weight_modifier(node) {
+                self.check_modifiers(script, node.children(), diagnostics);
+                continue;
+            }
+
+            // outside of effects and triggers we only look for blocks that hold them
+            let Some(context) = context else {
+                if node.is_block() {
+                    let context = ScriptVocabulary::block_context(key);
+                    self.walk(script, node.children(), context, diagnostics);
+                }
+                continue;
+            };
+
+            let block_context = ScriptVocabulary::block_context(key);
+            let is_keyword = match context {
+                ScriptContext::Effect => self.vocabulary.effect(key).is_some(),
+                ScriptContext::Trigger => {
+                    self.vocabulary.trigger(key).is_some()
+                        || self.vocabulary.script_value(key).is_some()
+                }
+            };
+
+            let is_argument = ARGUMENT_KEYS.contains(&key);
+            let is_link = !is_keyword && block_context.is_none() && !is_argument && {
+                match self.check_links(script, node, key) {
+                    Some(diagnostic) => {
+                        diagnostics.push(diagnostic);
+                        true
+                    }
+                    None => self.is_link(key),
+                }
+            };
+
+            if !is_keyword
+                && !is_link
+                && !is_argument
+                && block_context.is_none()
+                && !Self::is_dynamic(key)
+                && node.key.is_some()
+            {
+                let kind = match context {
+                    ScriptContext::Effect => ScriptReferenceKind::Effect,
+                    ScriptContext::Trigger => ScriptReferenceKind::Trigger,
+                };
+                diagnostics.push(self.unknown(script, node, key, kind));
+            }
+
+            if !node.is_block() || is_argument {
+                continue;
+            }
+
+            // the contents of most effects and triggers are their arguments, not more script
+            let holds_script = block_context.is_some()
+                || is_link
+                || Self::is_dynamic(key)
+                || CONTROL_BLOCKS.contains(&key)
+                || ITERATOR_PREFIXES.iter().any(|p| key.starts_with(p));
+
+            if holds_script {
+                self.walk(
+                    script,
+                    node.children(),
+                    Some(block_context.unwrap_or(context)),
+                    diagnostics,
+                );
+            }
+        }
+    }
+
+    fn is_weight_modifier(node: &ScriptNode) -> bool {
+        node.children()
+            .iter()
+            .any(|c| WEIGHT_MODIFIER_KEYS.contains(&c.key()))
+    }
+
+    /// Whether a key is a link or chain of links, like `owner.capital`.
+    fn is_link(&self, key: &str) -> bool {
+        key.split('.').all(|link| {
+            matches!(link, "this" | "root" | "prev")
+                || Self::is_dynamic(link)
+                || self.vocabulary.event_target(link).is_some()
+        })
+    }
+
+    /// Reports the first unknown link in a chain like `owner.capitl`. Single keys aren't reported
+    /// here, since they're more likely to be a misspelled effect or trigger.
+    fn check_links(&self, script: &ScriptFile, node: &ScriptNode, key: &str) -> Option<Diagnostic> {
+        if !key.contains('.') {
+            return None;
+        }
+
+        key.split('.')
+            .find(|link| !self.is_link(link))
+            .map(|link| self.unknown(script, node, link, ScriptReferenceKind::EventTarget))
+    }
+
+    /// Whether a key is something we can't check, like a saved scope, a parameter or a number.
+    fn is_dynamic(key: &str) -> bool {
+        key.contains(':') || key.contains('$') || key.starts_with('@') || key.parse::<f64>().is_ok()
+    }
+
+    fn unknown(
+        &self,
+        script: &ScriptFile,
+        node: &ScriptNode,
+        name: &str,
+        kind: ScriptReferenceKind,
+    ) -> Diagnostic {
+        let kind_name = match kind {
+            ScriptReferenceKind::Effect => "effect",
+            ScriptReferenceKind::Trigger => "trigger",
+            ScriptReferenceKind::EventTarget => "event target",
+            ScriptReferenceKind::Modifier => "modifier",
+        };
+
+        let suggestions = suggest(name, self.vocabulary.names(kind));
+        let message = match suggestions.is_empty() {
+            true => format!("unknown {} `{}`", kind_name, name),
+            false => format!(
+                "unknown {} `{}`, did you mean {}?",
+                kind_name,
+                name,
+                suggestions.iter().map(|s| format!("`{}`", s)).join(", ")
+            ),
+        };
+
+        Diagnostic {
+            path: script.path.clone(),
+            line: node.line,
+            message,
+        }
+    }
+}
+
+/// Returns up to three of `candidates` closest to `name` by edit distance, if they're close enough
+/// to plausibly be what was meant.
+fn suggest<'a>(name: &str, candidates: impl Iterator<Item = &'a str>) -> Vec<&'a str> {
+    let max_distance = (name.len() / 4).max(2);
+    candidates
+        .map(|c| (strsim::levenshtein(name, c), c))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .sorted()
+        .take(3)
+        .map(|(_, c)| c)
+        .collect()
+}
@@ -0,0 +1,59 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use log::{info, warn};
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::mods::{GameFiles, LayeredDefinitions};
+
+static LOC_LINE_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"^\s*([A-Za-z0-9_.\-']+):\d*\s*"(.*)"[^"]*$"#).unwrap());
+
+/// The game's localization in a single language, with any mods layered on top.
+#[derive(Default)]
+pub struct Localization {
+    strings: HashMap<String, String>,
+}
+
+impl Localization {
+    /// Reads every string in `language` (like `english`) from the game and its mods. Like the game,
+    /// files in a `replace` folder are loaded last, so their strings win over any others.
+    pub fn read(files: &GameFiles, language: &str) -> Result<Localization> {
+        let mut loc_files = files.find(&format!("localization/**/*_l_{}.yml", language))?;
+        loc_files.sort_by_key(|f| f.path.split('/').any(|c| c == "replace"));
+
+        let mut layered = LayeredDefinitions::new();
+        for file in &loc_files {
+            let bytes = match std::fs::read(&file.disk_path) {
+                Ok(bytes) => bytes,
+                Err(e) => {
+                    warn!("skipping localization file {}: {}", file.path, e);
+                    continue;
+                }
+            };
+
+            let text = String::from_utf8_lossy(&bytes);
+            for line in text.trim_start_matches('\u{feff}').lines() {
+                if let Some(captures) = LOC_LINE_REGEX.captures(line) {
+                    let value = captures[2].replace("\\\"", "\"");
+                    layered.insert_with(file, &captures[1], || (captures[1].to_string(), value));
+                }
+            }
+        }
+
+        let strings: HashMap<String, String> = layered
+            .into_definitions(files)
+            .into_iter()
+            .map(|(definition, _)| definition)
+            .collect();
+
+        info!("read {} {} localization strings", strings.len(), language);
+        Ok(Localization { strings })
+    }
+
+    /// The text of a string, if it's localized.
+    pub fn text(&self, key: &str) -> Option<&str> {
+        self.strings.get(key).map(|s| s.as_str())
+    }
+}
@@ -1,31 +1,166 @@
 #![feature(adt_const_params)]
-use std::{cell::RefCell, path::PathBuf, rc::Rc};
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    rc::Rc,
+};
 
-use anyhow::Result;
+use anyhow::{Error, Result};
+use archive::ArchiveFormat;
+use clap::{Parser, Subcommand, ValueEnum};
 use clauser::string_table::StringTable;
+use concepts::ConceptReader;
 use config::{Config, Profile, ProfileGame};
+use definitions::{DefinitionReader, ScriptDefinitionEntry};
+use diff::DossierDiff;
 use dossier::{DocInfo, Dossier};
-use error::Error;
+use events::EventReader;
 use games::GameDocProvider;
 use generator::SiteGenerator;
+use history::HistoryIndex;
 use itertools::Itertools;
+use lint::{IdentifierChecker, ScopeChecker};
+use localization::Localization;
 use log::info;
 use mapper::SiteMapper;
-use page::{GenericListPageBuilder, MaskPage, ScopePage};
-use theme::PackagedTheme;
+use modifier_types::ModifierTypeReader;
+use mods::{GameFiles, ModDescriptor};
+use page::{
+    ChangelogPage, GenericListPageBuilder, MaskPage, Page, ScopePage, UnusedReportPageBuilder,
+};
+use schema::ContextSchema;
+use script::ScriptVocabulary;
+use snapshot::DossierSnapshot;
+use theme::{PackagedTheme, Strings};
+use usage::UsageHarvester;
 
+mod archive;
+mod concepts;
 mod config;
+mod definitions;
+mod diff;
 mod dossier;
 mod entry;
 mod error;
+mod events;
 mod games;
 mod generator;
 mod helpers;
+mod history;
+mod lint;
+mod localization;
 mod mapper;
+mod modifier_types;
+mod mods;
 mod page;
+mod record;
+mod schema;
+mod script;
+mod snapshot;
 mod theme;
+mod usage;
 mod util;
 
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    /// The config file to read profiles from.
+    #[arg(short, long, default_value = "config.json")]
+    config: PathBuf,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Generates documentation for every profile. This is the default.
+    Generate,
+    /// Checks the script files of a profile's mods for mistakes.
+    Lint {
+        /// The profile whose mods should be checked. Defaults to the first profile.
+        #[arg(short, long)]
+        profile: Option<String>,
+        /// Which checks to run.
+        #[arg(short, long, value_enum, default_value_t = LintMode::All)]
+        mode: LintMode,
+    },
+    /// Saves a snapshot of a profile's documentation, to be loaded later in place of the game.
+    Snapshot {
+        /// The profile to take a snapshot of. Defaults to the first profile.
+        #[arg(short, long)]
+        profile: Option<String>,
+        /// The file to write the snapshot to.
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+    /// Writes a description of the data templates are rendered with, for theme authors, with a
+    /// sample of each template's data taken from a profile.
+    Schema {
+        /// The profile to take samples from. Defaults to the first profile.
+        #[arg(short, long)]
+        profile: Option<String>,
+        /// The JSON file to write the description to.
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+    /// Works with theme packages.
+    Theme {
+        #[command(subcommand)]
+        command: ThemeCommand,
+    },
+}
+
+#[derive(Subcommand)]
+enum ThemeCommand {
+    /// Packs a theme directory into a single archive that can be used as a theme.
+    Pack {
+        /// The theme directory, holding theme.json.
+        dir: PathBuf,
+        /// The archive to write, ending in .zip or .tar.gz.
+        #[arg(short, long)]
+        output: PathBuf,
+        /// Lets the theme run the programs it processes assets with while it's checked.
+        #[arg(long)]
+        allow_processors: bool,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum LintMode {
+    /// Checks that effects and triggers are used in scopes they support.
+    Scopes,
+    /// Checks for effects, triggers, event targets and modifiers that don't exist.
+    Identifiers,
+    /// Runs every check.
+    All,
+}
+
+/// The game's script files for a profile, with its mods layered on top.
+fn game_files(profile: &Profile) -> Result<GameFiles> {
+    let provider = games::provider_for_game(&profile.game);
+    let mods = profile
+        .mods
+        .iter()
+        .map(|dir| ModDescriptor::read(&PathBuf::from(dir)))
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(GameFiles::new(provider.script_dir(profile), mods))
+}
+
+fn find_profile<'c>(config: &'c Config, name: Option<&str>) -> Result<&'c Profile> {
+    match name {
+        Some(name) => config.profiles.iter().find(|p| p.name == name),
+        None => config.profiles.first(),
+    }
+    .ok_or(Error::msg(format!(
+        "Can't find profile {}",
+        name.unwrap_or("(no profiles configured)")
+    )))
+}
+
 fn process_profile(
     profile: &Profile,
     config: &Config,
@@ -33,6 +168,35 @@ fn process_profile(
 ) -> Result<Rc<Dossier>> {
     info!("processing profile {}", profile.name);
 
+    let dossier = Rc::new(match &profile.snapshot {
+        Some(snapshot) => {
+            info!("loading snapshot {}", snapshot);
+            DossierSnapshot::load(&PathBuf::from(snapshot))?.into_dossier(config, mapper)?
+        }
+        None => read_game(profile, config, mapper)?,
+    });
+
+    if !profile.history.is_empty() {
+        info!("reading history from {} snapshots", profile.history.len());
+        let snapshots = profile
+            .history
+            .iter()
+            .map(|path| DossierSnapshot::load(&PathBuf::from(path)))
+            .collect::<Result<Vec<_>>>()?;
+
+        let history = HistoryIndex::new(snapshots, dossier.clone())?;
+        let _ = dossier.history.set(history);
+    }
+
+    Ok(dossier)
+}
+
+/// Reads everything there is to document from the game's files.
+fn read_game(
+    profile: &Profile,
+    config: &Config,
+    mapper: Rc<RefCell<SiteMapper>>,
+) -> Result<Dossier> {
     let provider = games::provider_for_game(&profile.game);
     let version = provider.read_version_info(profile)?;
     info!(
@@ -43,10 +207,12 @@ fn process_profile(
     info!("parsing script docs");
     let mut script_docs = provider.read_script_docs(&profile)?;
 
-    let entries = match script_docs.as_mut() {
+    let mut entries: Vec<_> = match script_docs.as_mut() {
         Some(docs) => docs.entries.drain().map(|(_, v)| v).collect(),
         None => vec![],
     };
+    // entries are added in a fixed order, so cross references are always in the same order
+    entries.sort_by_key(|e| e.id);
 
     let scopes = script_docs
         .as_ref()
@@ -62,6 +228,14 @@ fn process_profile(
         None => StringTable::new(),
     };
 
+    let names = |indices: Vec<usize>| {
+        indices
+            .iter()
+            .map(|s| string_table.get(*s).unwrap())
+            .collect_vec()
+    };
+    let (scopes, masks) = (names(scopes), names(masks));
+
     let mut dossier = Dossier::new(
         config.clone(),
         provider.get_categories(profile)?,
@@ -73,28 +247,203 @@ fn process_profile(
     dossier.add_entries(entries.into_iter())?;
     info!("collected {} entries", dossier.entries.len());
 
+    let files = game_files(profile)?;
+
+    info!("parsing localization");
+    dossier.localization = Localization::read(&files, &profile.localization_language)?;
+
+    info!("parsing game concepts");
+    let concepts = ConceptReader::read(&files, &dossier.localization)?;
+    dossier.add_entries(concepts.into_iter())?;
+
+    info!("parsing modifier types");
+    let modifier_types = ModifierTypeReader::read(&files, &dossier.localization, &dossier)?;
+    dossier.add_entries(modifier_types.into_iter())?;
+
+    let mut vocabulary = ScriptVocabulary::from_dossier(&dossier);
+
+    info!("harvesting vanilla usages");
+    dossier.usages = UsageHarvester::new(&vocabulary, config.usage_examples)
+        .harvest(&provider.script_dir(profile))?;
+
+    info!("parsing script definitions");
+    let definitions = DefinitionReader::read(&files)?;
+    for definition in &definitions {
+        vocabulary.add_definition(definition.kind, &definition.name);
+    }
+
+    let entries = definitions
+        .into_iter()
+        .map(|d| ScriptDefinitionEntry::new(d, &vocabulary, &dossier))
+        .collect_vec();
+    dossier.add_entries(entries.into_iter())?;
+
+    info!("parsing events");
+    let (namespaces, events) = EventReader::read(&files, &dossier, &vocabulary)?;
+    dossier.add_entries(namespaces.into_iter())?;
+    dossier.add_entries(events.into_iter())?;
+
     dossier.add_builder(GenericListPageBuilder::<ScopePage>::new(scopes));
     dossier.add_builder(GenericListPageBuilder::<MaskPage>::new(masks));
+    dossier.add_builder(UnusedReportPageBuilder);
 
-    Ok(Rc::new(dossier))
+    Ok(dossier)
 }
 
-fn main() -> Result<()> {
-    colog::init();
+/// Lints a profile's mods, failing with a non-zero exit code if there are any problems.
+fn lint(config: &Config, profile: Option<&str>, mode: LintMode) -> Result<ExitCode> {
+    let profile = find_profile(config, profile)?;
+    if profile.mods.is_empty() {
+        return Err(Error::msg(format!(
+            "Profile {} doesn't have any mods to lint",
+            profile.name
+        )));
+    }
+
+    let mapper = Rc::new(RefCell::new(SiteMapper::new(config.clone())));
+    let dossier = process_profile(profile, config, mapper)?;
+    let files = game_files(profile)?;
+
+    let mut diagnostics = Vec::new();
+    if mode != LintMode::Identifiers {
+        diagnostics.extend(ScopeChecker::new(&dossier).check(&files)?);
+    }
+
+    if mode != LintMode::Scopes {
+        let vocabulary = ScriptVocabulary::from_dossier(&dossier);
+        diagnostics.extend(IdentifierChecker::new(&vocabulary).check(&files)?);
+    }
 
-    let config = Config::create(&PathBuf::from("config.json"))?;
-    let theme = PackagedTheme::new(&PathBuf::from(format!(
-        "{}/themes/default",
-        env!("CARGO_MANIFEST_DIR")
+    for diagnostic in &diagnostics {
+        println!("{}", diagnostic);
+    }
+
+    info!("found {} problems", diagnostics.len());
+    Ok(match diagnostics.is_empty() {
+        true => ExitCode::SUCCESS,
+        false => ExitCode::FAILURE,
+    })
+}
+
+fn snapshot(config: &Config, profile: Option<&str>, output: &Path) -> Result<()> {
+    let profile = find_profile(config, profile)?;
+    let mapper = Rc::new(RefCell::new(SiteMapper::new(config.clone())));
+    let dossier = process_profile(profile, config, mapper)?;
+
+    DossierSnapshot::new(dossier)?.save(output)
+}
+
+fn load_theme(config: &Config) -> Result<PackagedTheme> {
+    let path = match &config.theme {
+        Some(path) => path.clone(),
+        None => PackagedTheme::builtin_dir("default"),
+    };
+
+    PackagedTheme::new(&path, &config.theme_settings, config.allow_theme_processors)
+}
+
+fn pack_theme(dir: &Path, output: &Path, allow_processors: bool) -> Result<()> {
+    let format = ArchiveFormat::for_path(output).ok_or(Error::msg(format!(
+        "Can't tell the archive format of {:?}, which should end in .zip or .tar.gz",
+        output
     )))?;
 
-    let mut generator = SiteGenerator::new(&config);
+    // loaded first, so broken themes aren't shared around
+    PackagedTheme::new(dir, &BTreeMap::new(), allow_processors)?;
+    archive::pack(dir, output, format)
+}
+
+fn schema(config: &Config, profile: Option<&str>, output: &Path) -> Result<()> {
+    let theme = load_theme(config)?;
+
+    let profile = find_profile(config, profile)?;
+    let mut generator = SiteGenerator::new(config);
+    let dossier = process_profile(profile, config, generator.mapper.clone())?;
+    let _ = dossier.strings.set(Rc::new(Strings::new(&theme, config)?));
+    generator.add_profile(profile.clone(), dossier, vec![]);
+
+    let schema = ContextSchema::new(generator.sample_data(&theme)?);
+    fs::write(output, serde_json::to_string_pretty(&schema)?)?;
+    info!("wrote template context to {}", output.display());
+
+    Ok(())
+}
+
+/// Creates the changelog pages for a profile that's compared against another.
+fn changelog_pages(
+    profile: &Profile,
+    dossiers: &HashMap<String, Rc<Dossier>>,
+) -> Result<Vec<Box<dyn Page>>> {
+    let Some(other) = &profile.compare_to else {
+        return Ok(vec![]);
+    };
+
+    let old = dossiers.get(other).ok_or_else(|| {
+        Error::msg(format!(
+            "profile {} compares to unknown profile {}",
+            profile.name, other
+        ))
+    })?;
+    let dossier = dossiers[&profile.name].clone();
+    let diff = DossierDiff::between(old.clone(), dossier.clone())?;
+    Ok(ChangelogPage::create(dossier, diff))
+}
+
+fn generate(config: &Config) -> Result<()> {
+    let theme = load_theme(config)?;
+    let strings = Rc::new(Strings::new(&theme, config)?);
+
+    let mut generator = SiteGenerator::new(config);
+    let mut dossiers = HashMap::new();
+    for profile in &config.profiles {
+        let dossier = process_profile(profile, config, generator.mapper.clone())?;
+        let _ = dossier.strings.set(strings.clone());
+        dossiers.insert(profile.name.clone(), dossier);
+    }
+
     for profile in &config.profiles {
-        let dossier = process_profile(profile, &config, generator.mapper.clone())?;
-        generator.add_profile(profile.clone(), dossier);
+        let dossier = dossiers[&profile.name].clone();
+        let pages = changelog_pages(profile, &dossiers)?;
+        generator.add_profile(profile.clone(), dossier, pages);
+    }
+
+    if let Some(latest) = &config.latest {
+        let profile = find_profile(config, Some(latest))?;
+        let dossier = dossiers[&profile.name].clone();
+        let pages = changelog_pages(profile, &dossiers)?;
+
+        let mut alias = profile.clone();
+        alias.dir = Some("latest".into());
+        generator.add_profile(alias, dossier, pages);
     }
 
     generator.generate(&theme)?;
 
     Ok(())
 }
+
+fn main() -> Result<ExitCode> {
+    colog::init();
+
+    let cli = Cli::parse();
+    let command = cli.command.unwrap_or(Command::Generate);
+    // only read by the commands that need it, so themes can be packed without a config
+    let config = || Config::create(&cli.config);
+
+    match command {
+        Command::Generate => generate(&config()?)?,
+        Command::Lint { profile, mode } => return lint(&config()?, profile.as_deref(), mode),
+        Command::Snapshot { profile, output } => snapshot(&config()?, profile.as_deref(), &output)?,
+        Command::Schema { profile, output } => schema(&config()?, profile.as_deref(), &output)?,
+        Command::Theme {
+            command:
+                ThemeCommand::Pack {
+                    dir,
+                    output,
+                    allow_processors,
+                },
+        } => pack_theme(&dir, &output, allow_processors)?,
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
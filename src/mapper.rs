@@ -31,32 +31,41 @@ pub struct SiteMap {
 }
 
 impl SiteMap {
-    pub fn from_pages(profile: &SiteProfile) -> SiteMap {
+    /// Maps the pages of a profile, which must be the mapper's current profile.
+    pub fn from_pages(profile: &SiteProfile, mapper: &SiteMapper) -> SiteMap {
+        let dir = PathBuf::from(mapper.profile_dir(&profile.profile));
         let mut map: SiteMap = SiteMap {
             title: profile.profile.title.clone(),
             key: String::new(),
-            absolute_url: "/index.html".into(),
+            absolute_url: dir.join("index.html").to_str().unwrap().to_string(),
             children: HashMap::new(),
             page: None,
             page_ids: Vec::new(),
         };
 
-        for page in &profile.pages {
+        for page in profile.pages.iter().filter(|p| p.in_site_map()) {
             let p = PathBuf::from(page.info().path);
             let components = p.components().collect_vec();
-            map.fill_from_path(components.as_slice(), page.as_ref());
+            map.fill_from_path(components.as_slice(), page.as_ref(), &dir, mapper);
         }
 
         map
     }
 
-    fn fill_from_path(&mut self, components: &[Component], page: &dyn Page) {
+    fn fill_from_path(
+        &mut self,
+        components: &[Component],
+        page: &dyn Page,
+        dir: &Path,
+        mapper: &SiteMapper,
+    ) {
+        let page_id = mapper.scoped(page.id());
         if components.is_empty() {
             let info = page.info();
             self.title = info.short_title;
             self.page = info.pagination.clone();
-            self.page_ids.push(page.id());
-            let mut url = PathBuf::from(match info.pagination {
+            self.page_ids.push(page_id);
+            let mut url = dir.join(match info.pagination {
                 Some(pagination) if pagination.total_pages > 1 => page.page_url(1).clone(),
                 _ => info.path.clone(),
             });
@@ -65,18 +74,19 @@ impl SiteMap {
             return;
         }
 
-        self.page_ids.push(page.id());
+        self.page_ids.push(page_id);
 
         let first = components.first().unwrap();
         let first = first.as_os_str().to_str().unwrap().to_string();
         // this is an index - let's actually apply its properties here instead
         if first.starts_with("index.") {
-            self.fill_from_path(&components[1..], page);
+            self.fill_from_path(&components[1..], page, dir, mapper);
             return;
         }
 
         if let Some(prev) = self.children.get(&first) {
-            prev.borrow_mut().fill_from_path(&components[1..], page);
+            prev.borrow_mut()
+                .fill_from_path(&components[1..], page, dir, mapper);
             return;
         }
 
@@ -86,14 +96,25 @@ impl SiteMap {
             absolute_url: String::new(),
             children: HashMap::new(),
             page: None,
-            page_ids: vec![page.id()],
+            page_ids: vec![page_id],
         };
 
-        map.fill_from_path(&components[1..], page);
+        map.fill_from_path(&components[1..], page, dir, mapper);
         self.children.insert(first, map.into());
     }
 }
 
+/// A version of the site's pages that can be switched to from another, for the version selector.
+#[derive(Serialize, Clone, Debug)]
+pub struct VersionLink {
+    pub name: String,
+    /// A link to the equivalent of the current page in this version, if there is one.
+    pub url: Option<String>,
+    pub is_current: bool,
+}
+
+/// Page and entry IDs are only unique within a profile, so everything the mapper records is
+/// scoped to the profile it came from. The mapper resolves IDs against the current profile.
 pub struct SiteMapper {
     pub page_paths: HashMap<u64, SiteMapperPath>,
     pub groups: HashMap<u64, Vec<(usize, u64)>>,
@@ -103,8 +124,9 @@ pub struct SiteMapper {
     entry_pages: HashMap<u64, u64>,
     config: Config,
 
-    page_profiles: HashMap<u64, u64>,
-    profiles: HashMap<u64, Profile>,
+    /// The folder of every recorded profile, in the order they were recorded.
+    profiles: Vec<String>,
+    current_profile: u64,
 }
 
 impl SiteMapper {
@@ -114,13 +136,61 @@ impl SiteMapper {
             entry_anchors: HashMap::new(),
             entry_pages: HashMap::new(),
             config,
-            page_profiles: HashMap::new(),
-            profiles: HashMap::new(),
+            profiles: Vec::new(),
+            current_profile: 0,
             page_groups: HashMap::new(),
             groups: HashMap::new(),
         }
     }
 
+    /// The folder a profile's pages go in, relative to the output directory. This is empty if the
+    /// site only has the one profile and it isn't put in a subfolder.
+    pub fn profile_dir(&self, profile: &Profile) -> String {
+        if self.uses_profile_dirs() {
+            profile.dir().to_string()
+        } else {
+            String::new()
+        }
+    }
+
+    /// Whether each profile's pages go in a folder of their own, in which case the output
+    /// directory gets an index linking to each of them.
+    pub fn uses_profile_dirs(&self) -> bool {
+        self.config.profiles.len() > 1
+            || self.config.use_subfolder_for_single_profile
+            || self.config.latest.is_some()
+    }
+
+    /// The ID of the index at the root of the output directory.
+    pub fn root_page_id() -> u64 {
+        util::hash(&"ROOT_INDEX")
+    }
+
+    /// Records the index at the root of the output directory, which isn't part of any profile.
+    pub fn record_root(&mut self) {
+        self.page_paths.insert(
+            Self::root_page_id(),
+            SiteMapperPath {
+                disk: self.config.output_dir.join("index.html"),
+                path: "index.html".into(),
+            },
+        );
+    }
+
+    /// Sets the profile that page and entry IDs are resolved against.
+    pub fn set_current_profile(&mut self, profile: &Profile) {
+        self.current_profile = util::hash(&self.profile_dir(profile));
+    }
+
+    /// Turns an ID from the current profile into an ID that's unique across the whole site.
+    pub fn scoped(&self, id: u64) -> u64 {
+        Self::scoped_in(self.current_profile, id)
+    }
+
+    fn scoped_in(profile: u64, id: u64) -> u64 {
+        util::hash(&(profile, id))
+    }
+
     pub fn page_path_mapping(&self) -> HashMap<u64, String> {
         self.page_paths
             .iter()
@@ -130,7 +200,7 @@ impl SiteMapper {
 
     pub fn asset_url(&self, from_id: u64, item: &str) -> String {
         Self::url_from(
-            &PathBuf::from(&self.page_paths.get(&from_id).unwrap().path),
+            &PathBuf::from(&self.page_paths.get(&self.scoped(from_id)).unwrap().path),
             &PathBuf::from("/assets").join(item),
         )
     }
@@ -146,17 +216,24 @@ impl SiteMapper {
 
     pub fn page_to_entry_url(&self, from_page: &u64, to_entry: &u64) -> String {
         Self::url_from(
-            &PathBuf::from(&self.page_paths.get(&from_page).unwrap().path),
+            &PathBuf::from(&self.page_paths.get(&self.scoped(*from_page)).unwrap().path),
             &PathBuf::from(
                 &self
                     .page_paths
-                    .get(self.entry_pages.get(to_entry).unwrap())
+                    .get(self.entry_pages.get(&self.scoped(*to_entry)).unwrap())
                     .unwrap()
                     .path,
             ),
         )
     }
 
+    pub fn page_to_page_url(&self, from_page: &u64, to_page: &u64) -> String {
+        Self::url_from(
+            &PathBuf::from(&self.page_paths.get(&self.scoped(*from_page)).unwrap().path),
+            &PathBuf::from(&self.page_paths.get(&self.scoped(*to_page)).unwrap().path),
+        )
+    }
+
     pub fn asset_url_with_mapping(
         mapping: &HashMap<u64, String>,
         from_id: u64,
@@ -176,16 +253,14 @@ impl SiteMapper {
     }
 
     pub fn record_profile(&mut self, p: &SiteProfile) {
-        let profile_id = util::hash(&p.profile.name);
-        self.profiles.insert(profile_id, p.profile.clone());
+        let dir = self.profile_dir(&p.profile);
+        let profile_id = util::hash(&dir);
+        self.profiles.push(dir.clone());
 
         for page in &p.pages {
             let info = page.info();
-            let page_id = page.id();
-            let mut path = PathBuf::new();
-            if self.config.profiles.len() > 1 || self.config.use_subfolder_for_single_profile {
-                path.push(&p.profile.name);
-            }
+            let page_id = Self::scoped_in(profile_id, page.id());
+            let mut path = PathBuf::from(&dir);
             path.push(info.path);
             path.set_extension("html");
 
@@ -200,7 +275,7 @@ impl SiteMapper {
             );
 
             if let Some(pagination) = info.pagination {
-                let group_id = page.group_id();
+                let group_id = Self::scoped_in(profile_id, page.group_id());
                 self.page_groups.insert(page_id, group_id);
                 let group = match self.groups.entry(group_id) {
                     Entry::Occupied(entries) => entries.into_mut(),
@@ -211,29 +286,69 @@ impl SiteMapper {
             }
 
             for id in page.entries() {
-                self.entry_pages.insert(id, page_id);
+                self.entry_pages
+                    .insert(Self::scoped_in(profile_id, id), page_id);
             }
 
             for (id, anchor) in page.anchors() {
-                self.entry_anchors.insert(id, anchor);
+                self.entry_anchors
+                    .insert(Self::scoped_in(profile_id, id), anchor);
             }
+        }
+    }
 
-            self.page_profiles.insert(page_id, profile_id);
+    /// Links to the equivalent of a page of the current profile in every profile. That's the same
+    /// page if it exists there, or else the page its first entry is on, or else the first page
+    /// of its group, like the first page of its category.
+    pub fn version_links(&self, page: &dyn Page) -> Vec<VersionLink> {
+        if self.profiles.len() < 2 {
+            return vec![];
         }
+
+        let from = self.page_paths.get(&self.scoped(page.id())).unwrap();
+        self.profiles
+            .iter()
+            .map(|dir| {
+                let profile_id = util::hash(dir);
+                let scoped = |id| Self::scoped_in(profile_id, id);
+
+                let target = self
+                    .page_paths
+                    .get(&scoped(page.id()))
+                    .map(|p| p.path.clone())
+                    .or_else(|| {
+                        let entry = scoped(*page.entries().first()?);
+                        let path = &self.page_paths.get(self.entry_pages.get(&entry)?)?.path;
+                        Some(match self.entry_anchors.get(&entry) {
+                            Some(anchor) => format!("{}#{}", path, anchor),
+                            None => path.clone(),
+                        })
+                    })
+                    .or_else(|| {
+                        let group = self.groups.get(&scoped(page.group_id()))?;
+                        let (_, first) = group.iter().min_by_key(|(num, _)| *num)?;
+                        Some(self.page_paths.get(first)?.path.clone())
+                    });
+
+                VersionLink {
+                    name: dir.clone(),
+                    url: target
+                        .map(|t| Self::url_from(&PathBuf::from(&from.path), &PathBuf::from(t))),
+                    is_current: profile_id == self.current_profile,
+                }
+            })
+            .collect()
     }
 
-    pub fn url_for_entry(&self, from_id: u64, to_id: u64) -> String {
-        let to_path = self
-            .page_paths
-            .get(self.entry_pages.get(&to_id).unwrap())
-            .unwrap();
+    /// The URL of an entry as linked from the page with the ID `from_page`, or `None` if the entry
+    /// isn't on any page.
+    pub fn entry_url_from_page(&self, from_page: u64, to_id: u64) -> Option<String> {
+        let (from_page, to_id) = (self.scoped(from_page), self.scoped(to_id));
+        let to_path = self.page_paths.get(self.entry_pages.get(&to_id)?)?;
 
         let url = match &self.config.url_scheme {
             UrlScheme::Relative => {
-                let from_path = self
-                    .page_paths
-                    .get(self.entry_pages.get(&from_id).unwrap())
-                    .unwrap();
+                let from_path = self.page_paths.get(&from_page)?;
                 // diff the two paths to generate a relative URL
                 let to_path = PathBuf::from(&to_path.path);
                 Self::url_from(&PathBuf::from(&from_path.path), &to_path)
@@ -241,10 +356,10 @@ impl SiteMapper {
             UrlScheme::Absolute { base_url } => format!("{}{}", &base_url, &to_path.path),
         };
 
-        match self.entry_anchors.get(&to_id) {
+        Some(match self.entry_anchors.get(&to_id) {
             Some(anchor) => format!("{}#{}", url, anchor),
             None => url,
-        }
+        })
     }
 
     fn url_from(source: &Path, dest: &Path) -> String {
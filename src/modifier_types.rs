@@ -0,0 +1,191 @@
+use std::{collections::HashMap, rc::Rc};
+
+use anyhow::Result;
+use clauser::data::script_doc_parser::{
+    doc_string::{DocString, DocStringSegment},
+    ScriptDocCategory, ScriptDocEntry,
+};
+use handlebars::html_escape;
+use itertools::Itertools;
+use log::{info, warn};
+
+use crate::{
+    dossier::Dossier,
+    entry::{DocEntry, DEFINED_IN_PROPERTY},
+    localization::Localization,
+    mods::{GameFiles, LayeredDefinitions, Origin},
+    page::PageContext,
+    script::{ScriptFile, ScriptNode},
+    util::{self, code_list},
+};
+
+/// A modifier type defined in `common/modifier_type_definitions`, which mods use to add modifiers
+/// the game's script docs don't know about.
+pub struct ModifierTypeEntry {
+    id: u64,
+    name: String,
+    display_name: Option<String>,
+    percent: bool,
+    boolean: bool,
+    decimals: Option<String>,
+    color: Option<String>,
+    path: String,
+    line: usize,
+    /// The modifier in the game's script docs with the same name, if there is one.
+    documented: Option<u64>,
+    origin: Option<Origin>,
+}
+
+impl ModifierTypeEntry {
+    pub fn category_id() -> u64 {
+        util::hash(&"MODIFIER_TYPES")
+    }
+
+    pub fn entry_id_for_name(name: &str) -> u64 {
+        util::hash(&format!("modifier_type_{}", name))
+    }
+
+    fn new(
+        name: &str,
+        path: &str,
+        node: &ScriptNode,
+        localization: &Localization,
+        documented: Option<u64>,
+    ) -> ModifierTypeEntry {
+        ModifierTypeEntry {
+            id: Self::entry_id_for_name(name),
+            name: name.to_string(),
+            display_name: localization.text(name).map(|s| s.to_string()),
+            percent: node.get_scalar("percent") == Some("yes"),
+            boolean: node.get_scalar("boolean") == Some("yes"),
+            decimals: node.get_scalar("decimals").map(|s| s.to_string()),
+            color: node.get_scalar("color").map(|s| s.to_string()),
+            path: path.to_string(),
+            line: node.line,
+            documented,
+            origin: None,
+        }
+    }
+}
+
+impl DocEntry for ModifierTypeEntry {
+    fn id(&self) -> u64 {
+        self.id
+    }
+
+    fn category_id(&self) -> Option<u64> {
+        Some(Self::category_id())
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn record_cross_references(&self, dossier: &mut Dossier) {
+        if let Some(documented) = self.documented {
+            dossier.add_reference("Documented As", self.id, documented);
+        }
+    }
+
+    fn body(&self) -> Option<DocString> {
+        None
+    }
+
+    fn origin(&self) -> Option<&Origin> {
+        self.origin.as_ref()
+    }
+
+    fn properties(&self, context: &PageContext, dossier: Rc<Dossier>) -> Vec<(String, DocString)> {
+        let mut properties = Vec::new();
+
+        if let Some(display_name) = &self.display_name {
+            properties.push((
+                "Display Name".into(),
+                DocString::from(DocStringSegment::Text {
+                    contents: html_escape(display_name),
+                }),
+            ));
+        }
+
+        if let Some(documented) = self.documented {
+            properties.push((
+                "Documented As".into(),
+                dossier
+                    .link_for_entry(context, &self.name, &documented)
+                    .into(),
+            ));
+        }
+
+        properties.push(("Percent".into(), self.percent.into()));
+        properties.push(("Boolean".into(), self.boolean.into()));
+        if let Some(decimals) = &self.decimals {
+            properties.push(("Decimals".into(), code_list(std::iter::once(decimals))));
+        }
+
+        if let Some(color) = &self.color {
+            properties.push(("Color".into(), code_list(std::iter::once(color))));
+        }
+
+        properties.push((
+            DEFINED_IN_PROPERTY.into(),
+            code_list(std::iter::once(&format!("{}:{}", self.path, self.line))),
+        ));
+
+        properties
+    }
+}
+
+/// Reads modifier types from `common/modifier_type_definitions` of a game and its mods.
+pub struct ModifierTypeReader;
+
+impl ModifierTypeReader {
+    pub fn read(
+        files: &GameFiles,
+        localization: &Localization,
+        dossier: &Dossier,
+    ) -> Result<Vec<ModifierTypeEntry>> {
+        let documented: HashMap<&str, u64> = dossier
+            .entries
+            .values()
+            .filter_map(|e| e.as_any().downcast_ref::<ScriptDocEntry>())
+            .filter(|e| e.category == ScriptDocCategory::Modifiers)
+            .map(|e| (e.name.as_str(), e.id))
+            .collect();
+
+        let mut modifier_types = LayeredDefinitions::new();
+
+        for file in files.find("common/modifier_type_definitions/**/*.txt")? {
+            let script = match ScriptFile::read(&file.disk_path, &file.path) {
+                Ok(script) => script,
+                Err(e) => {
+                    warn!("skipping modifier type file {}: {}", file.path, e);
+                    continue;
+                }
+            };
+
+            for node in script
+                .nodes
+                .iter()
+                .filter(|n| n.is_block() && n.key.is_some())
+            {
+                modifier_types.insert_with(&file, node.key(), || {
+                    let documented = documented.get(node.key()).copied();
+                    ModifierTypeEntry::new(node.key(), &file.path, node, localization, documented)
+                });
+            }
+        }
+
+        let modifier_types = modifier_types
+            .into_definitions(files)
+            .into_iter()
+            .map(|(modifier_type, origin)| ModifierTypeEntry {
+                origin,
+                ..modifier_type
+            })
+            .sorted_by(|a, b| a.name.cmp(&b.name))
+            .collect_vec();
+
+        info!("read {} modifier types", modifier_types.len());
+        Ok(modifier_types)
+    }
+}
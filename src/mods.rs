@@ -0,0 +1,261 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Error, Result};
+use handlebars::html_escape;
+use log::info;
+use serde::Deserialize;
+
+use crate::{
+    dossier::Dossier,
+    script::{find_script_files, ScriptFile},
+};
+
+/// A mod, read from its `descriptor.mod` or `.metadata/metadata.json`.
+#[derive(Debug, Clone)]
+pub struct ModDescriptor {
+    pub name: String,
+    pub version: Option<String>,
+    pub dir: PathBuf,
+    /// Directories whose files from the game and earlier mods are ignored entirely.
+    pub replace_paths: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct ModMetadata {
+    name: String,
+    version: Option<String>,
+    #[serde(default)]
+    game_custom_data: ModMetadataCustomData,
+}
+
+#[derive(Deserialize, Default)]
+struct ModMetadataCustomData {
+    #[serde(default)]
+    replace_paths: Vec<String>,
+}
+
+impl ModDescriptor {
+    /// Reads the descriptor of the mod in `dir`, preferring `.metadata/metadata.json` over `descriptor.mod`.
+    pub fn read(dir: &Path) -> Result<ModDescriptor> {
+        let metadata_path = dir.join(".metadata").join("metadata.json");
+        if metadata_path.is_file() {
+            let metadata: ModMetadata = serde_json::from_str(&fs::read_to_string(metadata_path)?)?;
+            return Ok(ModDescriptor {
+                name: metadata.name,
+                version: metadata.version,
+                dir: dir.to_path_buf(),
+                replace_paths: Self::normalize_paths(metadata.game_custom_data.replace_paths),
+            });
+        }
+
+        let descriptor_path = dir.join("descriptor.mod");
+        if descriptor_path.is_file() {
+            let descriptor = ScriptFile::read(&descriptor_path, "descriptor.mod")?;
+            let scalar = |key: &str| {
+                descriptor
+                    .nodes
+                    .iter()
+                    .find(|n| n.key() == key)
+                    .and_then(|n| n.scalar())
+                    .map(|s| s.to_string())
+            };
+
+            return Ok(ModDescriptor {
+                name: scalar("name").unwrap_or_else(|| Self::dir_name(dir)),
+                version: scalar("version"),
+                dir: dir.to_path_buf(),
+                replace_paths: Self::normalize_paths(
+                    descriptor
+                        .nodes
+                        .iter()
+                        .filter(|n| n.key() == "replace_path")
+                        .filter_map(|n| n.scalar())
+                        .map(|s| s.to_string())
+                        .collect(),
+                ),
+            });
+        }
+
+        Err(Error::msg(format!(
+            "Can't find descriptor.mod or .metadata/metadata.json in {:?}",
+            dir
+        )))
+    }
+
+    fn normalize_paths(paths: Vec<String>) -> Vec<String> {
+        paths
+            .into_iter()
+            .map(|p| p.replace("\\", "/").trim_matches('/').to_string())
+            .collect()
+    }
+
+    fn dir_name(dir: &Path) -> String {
+        dir.file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default()
+    }
+}
+
+/// Where something read from game files comes from, once mods are layered over the game.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Origin {
+    Vanilla,
+    /// Defined by the game, but replaced by the named mod.
+    OverriddenBy(String),
+    /// Only defined by the named mod.
+    AddedBy(String),
+}
+
+impl Origin {
+    /// A badge to show next to an entry's name, as HTML.
+    pub fn badge(&self, dossier: &Dossier) -> String {
+        let (class, text) = match self {
+            Origin::Vanilla => ("vanilla", dossier.string("origin_vanilla", &[])),
+            Origin::OverriddenBy(name) => (
+                "overridden",
+                dossier.string("origin_overridden_by", &[("name", name)]),
+            ),
+            Origin::AddedBy(name) => (
+                "added",
+                dossier.string("origin_added_by", &[("name", name)]),
+            ),
+        };
+
+        format!(
+            "<span class=\"pd-origin pd-origin-{}\">{}</span>",
+            class,
+            html_escape(&text)
+        )
+    }
+}
+
+/// A script file from the game or one of its mods.
+pub struct GameFile {
+    pub disk_path: PathBuf,
+    /// The path of this file relative to the game (or mod) root, with forward slashes.
+    pub path: String,
+    /// The index of the layer this file comes from, where 0 is the game itself.
+    pub layer: usize,
+    /// Whether this file is replaced by a later layer and so never loaded by the game.
+    pub shadowed: bool,
+}
+
+/// The game's script files with any mods layered on top in load order.
+///
+/// Like the game, a file in a mod replaces the file at the same path in the game or an earlier mod,
+/// and a mod's `replace_path`s hide every earlier file directly inside those directories.
+pub struct GameFiles {
+    root: PathBuf,
+    mods: Vec<ModDescriptor>,
+}
+
+impl GameFiles {
+    pub fn new(root: PathBuf, mods: Vec<ModDescriptor>) -> GameFiles {
+        for descriptor in &mods {
+            info!(
+                "layering mod {} {} from {:?}",
+                descriptor.name,
+                descriptor.version.as_deref().unwrap_or(""),
+                descriptor.dir
+            );
+        }
+
+        GameFiles { root, mods }
+    }
+
+    pub fn has_mods(&self) -> bool {
+        !self.mods.is_empty()
+    }
+
+    /// Returns every file matching `glob` in each layer, in the order the game loads them.
+    /// Shadowed files are included so that callers can tell what they override.
+    pub fn find(&self, glob: &str) -> Result<Vec<GameFile>> {
+        let roots = std::iter::once(&self.root).chain(self.mods.iter().map(|m| &m.dir));
+
+        // files are loaded in order of their path, with later layers replacing earlier ones
+        let mut files: BTreeMap<String, Vec<GameFile>> = BTreeMap::new();
+        for (layer, root) in roots.enumerate() {
+            if let Some(descriptor) = layer.checked_sub(1).map(|i| &self.mods[i]) {
+                for file in files.values_mut().flatten() {
+                    let dir = file.path.rsplit_once('/').map(|(d, _)| d).unwrap_or("");
+                    if descriptor.replace_paths.iter().any(|p| p == dir) {
+                        file.shadowed = true;
+                    }
+                }
+            }
+
+            for (disk_path, path) in find_script_files(root, glob)? {
+                let versions = files.entry(path.clone()).or_default();
+                for earlier in versions.iter_mut() {
+                    earlier.shadowed = true;
+                }
+
+                versions.push(GameFile {
+                    disk_path,
+                    path,
+                    layer,
+                    shadowed: false,
+                });
+            }
+        }
+
+        Ok(files.into_values().flatten().collect())
+    }
+
+    /// Returns the origin of something whose active definition comes from `layer`.
+    pub fn origin(&self, layer: usize, in_vanilla: bool) -> Origin {
+        match layer.checked_sub(1).map(|i| &self.mods[i]) {
+            None => Origin::Vanilla,
+            Some(descriptor) if in_vanilla => Origin::OverriddenBy(descriptor.name.clone()),
+            Some(descriptor) => Origin::AddedBy(descriptor.name.clone()),
+        }
+    }
+}
+
+/// Collects named definitions from layered game files, keeping the last active definition of
+/// each name and remembering which names the game itself defines.
+pub struct LayeredDefinitions<T> {
+    vanilla: HashSet<String>,
+    definitions: HashMap<String, (usize, T)>,
+}
+
+impl<T> LayeredDefinitions<T> {
+    pub fn new() -> LayeredDefinitions<T> {
+        LayeredDefinitions {
+            vanilla: HashSet::new(),
+            definitions: HashMap::new(),
+        }
+    }
+
+    /// Records a definition from `file`, only creating it if the file is actually loaded.
+    pub fn insert_with<F: FnOnce() -> T>(&mut self, file: &GameFile, name: &str, create: F) {
+        if file.layer == 0 {
+            self.vanilla.insert(name.to_string());
+        }
+
+        if !file.shadowed {
+            // later definitions replace earlier ones, like the game does
+            self.definitions
+                .insert(name.to_string(), (file.layer, create()));
+        }
+    }
+
+    /// Returns every active definition, along with its origin if any mods are loaded.
+    pub fn into_definitions(self, files: &GameFiles) -> Vec<(T, Option<Origin>)> {
+        let vanilla = self.vanilla;
+        self.definitions
+            .into_iter()
+            .map(|(name, (layer, definition))| {
+                let origin = match files.has_mods() {
+                    true => Some(files.origin(layer, vanilla.contains(&name))),
+                    false => None,
+                };
+                (definition, origin)
+            })
+            .collect()
+    }
+}
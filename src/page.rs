@@ -1,21 +1,28 @@
 use std::{
-    cell::RefCell, collections::HashMap, hash::Hash, marker::PhantomData, path::PathBuf, rc::Rc,
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    hash::Hash,
+    marker::PhantomData,
+    path::{Path, PathBuf},
+    rc::Rc,
 };
 
-use clauser::data::script_doc_parser::{
-    doc_string::{DocString, DocStringSegment},
-    ScriptDocContent, ScriptDocEntry,
-};
+use clauser::data::script_doc_parser::doc_string::{DocString, DocStringSegment};
+use handlebars::html_escape;
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 use serde_json::{value::RawValue, Value};
 
 use crate::{
     config::Config,
-    dossier::{CollatedCrossReferences, DocCategory, Dossier},
+    diff::{ChangeKind, DossierDiff},
+    dossier::{CollatedCrossReferences, DocCategory, DocInfo, Dossier},
     entry::{DocEntry, EmptyDocEntry},
     generator::SiteProfile,
+    history::HistoryIndex,
     mapper::SiteMapper,
+    record::RecordValue,
+    script::ScriptReferenceKind,
     theme::Template,
     util::{self, paginate, DocStringSer},
 };
@@ -43,36 +50,42 @@ impl Breadcrumbs {
         Breadcrumbs { crumbs }
     }
 
-    /// Creates a new set of breadcrumbs from a page and its parents.
-    pub fn from_page(page: &dyn Page, profile: &SiteProfile) -> Breadcrumbs {
+    /// Creates a new set of breadcrumbs from a page and its parents, where `dir` is the folder
+    /// the profile's pages are in.
+    pub fn from_page(page: &dyn Page, profile: &SiteProfile, dir: &Path) -> Breadcrumbs {
         let mut crumbs = vec![Breadcrumb::Single {
             title: profile.profile.title.clone(),
-            absolute_url: "index".into(),
+            absolute_url: Self::in_dir(dir, "index.html"),
         }];
 
-        crumbs.extend(Self::from_page_inner(page, profile).crumbs);
+        // the first crumb already leads to the landing page
+        if page.id() == LandingPage::page_id() {
+            return Breadcrumbs { crumbs };
+        }
+
+        crumbs.extend(Self::from_page_inner(page, profile, dir).crumbs);
         Breadcrumbs { crumbs }
     }
 
-    fn from_page_inner(page: &dyn Page, profile: &SiteProfile) -> Breadcrumbs {
+    fn from_page_inner(page: &dyn Page, profile: &SiteProfile, dir: &Path) -> Breadcrumbs {
         let mut crumbs = Vec::new();
 
         // get parent crumbs if we have them
         if let Some(parent_id) = page.parent_id() {
             let parent = profile.pages.iter().find(|p| p.id() == parent_id).unwrap();
-            crumbs.extend(Self::from_page_inner(parent.as_ref(), profile).crumbs);
+            crumbs.extend(Self::from_page_inner(parent.as_ref(), profile, dir).crumbs);
         }
 
         let page_info = page.info();
         let crumb = match page_info.pagination {
             Some(pagination) if pagination.total_pages > 1 => Breadcrumb::Paged {
                 title: page_info.short_title.clone(),
-                root_url: Self::ensure_html_on_url(&page.page_url(1)),
+                root_url: Self::in_dir(dir, &Self::ensure_html_on_url(&page.page_url(1))),
                 page: pagination.clone(),
             },
             _ => Breadcrumb::Single {
                 title: page_info.short_title.clone(),
-                absolute_url: Self::ensure_html_on_url(&page_info.path),
+                absolute_url: Self::in_dir(dir, &Self::ensure_html_on_url(&page_info.path)),
             },
         };
 
@@ -84,6 +97,10 @@ impl Breadcrumbs {
         self.crumbs.len()
     }
 
+    fn in_dir(dir: &Path, url: &str) -> String {
+        dir.join(url).to_str().unwrap().to_string()
+    }
+
     fn ensure_html_on_url(str: &str) -> String {
         let mut path = PathBuf::from(str);
         path.set_extension("html");
@@ -101,19 +118,27 @@ impl IntoIterator for Breadcrumbs {
     }
 }
 
+/// URLs starting with this refer to an entry by ID, rather than to a page.
+pub const ENTRY_URL_PREFIX: &str = "entry:";
+
+/// A URL referring to the entry with the given ID, which is resolved when a page is rendered.
+pub fn entry_url(id: u64) -> String {
+    format!("{}{}", ENTRY_URL_PREFIX, id)
+}
+
 pub struct PageContext {
     mapper: Rc<RefCell<SiteMapper>>,
 }
 
 impl PageContext {
     pub fn new(mapper: Rc<RefCell<SiteMapper>>) -> PageContext {
-        PageContext {
-            mapper: mapper.clone(),
-        }
+        PageContext { mapper }
     }
 
-    pub fn url_for_entry(&self, from: &dyn DocEntry, entry: &dyn DocEntry) -> String {
-        self.mapper.borrow().url_for_entry(from.id(), entry.id())
+    /// Links to entries use `entry:<id>` URLs, which are resolved relative to whichever page the
+    /// link ends up rendered on, and are left as they are when entries are saved or compared.
+    pub fn url_for_entry(&self, entry: &dyn DocEntry) -> String {
+        entry_url(entry.id())
     }
 }
 
@@ -153,6 +178,10 @@ pub trait Page {
     /// All of the anchors (destinations that can be reached with the URL hash) on this page, and their entry ID.
     fn anchors(&self) -> Vec<(u64, String)>;
     fn data(&self, context: &PageContext) -> serde_json::Value;
+    /// Whether the page is listed in the site map.
+    fn in_site_map(&self) -> bool {
+        true
+    }
 }
 
 /// Object that produces pages.
@@ -168,6 +197,91 @@ pub trait GenericListPage: Page + Sized {
     fn index_page(dossier: Rc<Dossier>, entries: &[(u64, Rc<String>)]) -> Option<Box<dyn Page>>;
 }
 
+#[derive(Serialize)]
+struct Property {
+    name: String,
+    value: DocStringSer,
+}
+
+#[derive(Serialize)]
+struct Example {
+    location: String,
+    code: DocStringSer,
+}
+
+/// Everything shown about an entry, on its category's page or on a page of its own.
+#[derive(Serialize)]
+struct EntryData {
+    anchor: String,
+    name: String,
+    origin: Option<String>,
+    history: Option<String>,
+    body: Option<DocStringSer>,
+    properties: Vec<Property>,
+    examples: Vec<Example>,
+    cross_refs: CollatedCrossReferences,
+}
+
+impl EntryData {
+    fn new(dossier: &Rc<Dossier>, id: u64, page_id: u64, context: &PageContext) -> EntryData {
+        let entry = dossier.entries.get(&id).unwrap();
+        let mut properties = entry.properties(context, dossier.clone());
+        if let Some(count) = dossier.usages.count(entry.id()) {
+            properties.push((
+                dossier.string("vanilla_usages", &[]),
+                usage_count(dossier, count),
+            ));
+        }
+
+        let history = dossier.history.get().and_then(|h| h.get(entry.id()));
+        if let Some(history) = history {
+            let text = |contents: String| DocString::from(DocStringSegment::Text { contents });
+            properties.push((
+                dossier.string("introduced_in", &[]),
+                text(history.introduced_in.clone()),
+            ));
+            if !history.changed_in.is_empty() {
+                properties.push((
+                    dossier.string("changed_in", &[]),
+                    text(history.changed_in.join(", ")),
+                ));
+            }
+        }
+
+        let body = entry.body();
+        EntryData {
+            anchor: entry.name().to_owned(),
+            name: entry.name().to_owned(),
+            origin: entry.origin().map(|o| o.badge(dossier)),
+            history: history.map(|h| h.badge(dossier)),
+            body: body.and_then(|d| Some(DocStringSer(d, page_id, context.mapper.clone()))),
+            properties: properties
+                .drain(..)
+                .map(|(name, val)| Property {
+                    name,
+                    value: DocStringSer(val, page_id, context.mapper.clone()),
+                })
+                .collect(),
+            examples: dossier
+                .usages
+                .examples(entry.id())
+                .iter()
+                .map(|e| Example {
+                    location: format!("{}:{}", e.path, e.line),
+                    code: DocStringSer(
+                        DocString::new_from_segment(DocStringSegment::Code {
+                            contents: e.code.clone(),
+                        }),
+                        page_id,
+                        context.mapper.clone(),
+                    ),
+                })
+                .collect(),
+            cross_refs: Dossier::collate_references(dossier.clone(), context, page_id, entry.id()),
+        }
+    }
+}
+
 pub struct CategoryListPage {
     category: DocCategory,
     dossier: Rc<Dossier>,
@@ -196,7 +310,7 @@ impl Page for CategoryListPage {
         PageInfo {
             title: self.category.display_name.clone(),
             short_title: self.category.display_name.clone(),
-            template: Template::CategoryList,
+            template: Template::specialized("category_list", &self.category.name),
             path: match self.page.total_pages {
                 1 => self.category.name.clone(),
                 _ => Self::page_url(&self, self.page.current_page),
@@ -210,56 +324,35 @@ impl Page for CategoryListPage {
     }
 
     fn data(&self, context: &PageContext) -> serde_json::Value {
-        #[derive(Serialize)]
-        struct Property {
-            name: String,
-            value: DocStringSer,
-        }
-
-        #[derive(Serialize)]
-        struct Entry {
-            anchor: String,
-            name: String,
-            body: Option<DocStringSer>,
-            properties: Vec<Property>,
-            cross_refs: CollatedCrossReferences,
-        }
-
         #[derive(Serialize)]
         struct Data {
             body: DocStringSer,
-            entries: Vec<Entry>,
+            entries: Vec<EntryData>,
+            /// A link to the entries removed from this category, if there are any.
+            removed_url: Option<String>,
             pagination: PaginationInfo,
         }
 
-        let mut entries = Vec::new();
-        for entry in &self.entries {
-            let entry = self.dossier.entries.get(&entry).unwrap();
-            let mut properties = entry.properties(context, self.dossier.clone());
-            let body = entry.body();
-            entries.push(Entry {
-                anchor: entry.name().to_owned(),
-                name: entry.name().to_owned(),
-                body: body.and_then(|d| Some(DocStringSer(d, self.id(), context.mapper.clone()))),
-                properties: properties
-                    .drain(..)
-                    .map(|(name, val)| Property {
-                        name,
-                        value: DocStringSer(val, self.id(), context.mapper.clone()),
-                    })
-                    .collect(),
-                cross_refs: Dossier::collate_references(
-                    self.dossier.clone(),
-                    context,
-                    self.id(),
-                    entry.id(),
-                ),
-            });
-        }
+        let entries = self
+            .entries
+            .iter()
+            .map(|id| EntryData::new(&self.dossier, *id, self.id(), context))
+            .collect_vec();
+
+        let removed_url = match self.dossier.history.get() {
+            Some(history) if history.removed_in(&self.category.name).next().is_some() => Some(
+                context
+                    .mapper
+                    .borrow()
+                    .page_to_page_url(&self.id(), &TombstonePage::page_id(&self.category.name)),
+            ),
+            _ => None,
+        };
 
         serde_json::to_value(Data {
             body: DocStringSer(DocString::default(), self.id(), context.mapper.clone()),
             entries,
+            removed_url,
             pagination: self.page.clone(),
         })
         .unwrap()
@@ -296,12 +389,12 @@ impl Page for CategoryListPage {
 }
 
 pub struct GenericListPageBuilder<P: Page + GenericListPage> {
-    items: Vec<usize>,
+    items: Vec<Rc<String>>,
     _phantom: PhantomData<P>,
 }
 
 impl<P: GenericListPage + 'static> GenericListPageBuilder<P> {
-    pub fn new(items: Vec<usize>) -> GenericListPageBuilder<P> {
+    pub fn new(items: Vec<Rc<String>>) -> GenericListPageBuilder<P> {
         GenericListPageBuilder {
             items,
             _phantom: PhantomData::default(),
@@ -310,16 +403,15 @@ impl<P: GenericListPage + 'static> GenericListPageBuilder<P> {
 }
 
 impl<P: GenericListPage + 'static> PageBuilder for GenericListPageBuilder<P> {
-    fn build_entries(&self, dossier: &Dossier, _config: &Config) -> Vec<Box<dyn DocEntry>> {
+    fn build_entries(&self, _dossier: &Dossier, _config: &Config) -> Vec<Box<dyn DocEntry>> {
         let category_id = P::category_id();
         self.items
             .iter()
-            .map(move |s| dossier.string_table.get(*s).unwrap())
             .map(move |s| {
                 Box::new(EmptyDocEntry::new(
                     P::entry_id_for_name(s.as_str()),
                     category_id,
-                    (*s).clone(),
+                    (**s).clone(),
                 )) as Box<dyn DocEntry>
             })
             .collect_vec()
@@ -331,8 +423,7 @@ impl<P: GenericListPage + 'static> PageBuilder for GenericListPageBuilder<P> {
         let mut entry_ids = self
             .items
             .iter()
-            .map(|s| dossier.string_table.get(*s).unwrap())
-            .map(|name| (P::entry_id_for_name(name.as_str()), name))
+            .map(|name| (P::entry_id_for_name(name.as_str()), name.clone()))
             .collect_vec();
 
         entry_ids.sort_by_key(|(_, name)| name.as_str().to_owned());
@@ -376,7 +467,7 @@ impl Page for IndexPage {
         PageInfo {
             title: self.title.clone(),
             short_title: self.title.clone(),
-            template: Template::ListIndex,
+            template: Template::new("list_index"),
             path: self.path.clone(),
             pagination: None,
         }
@@ -469,9 +560,9 @@ impl Page for ScopePage {
     fn info(&self) -> PageInfo {
         PageInfo {
             short_title: self.name.clone(),
-            title: format!("Scope: {}", self.name),
+            title: self.dossier.string("scope_title", &[("name", &self.name)]),
             path: format!("scopes/{}", self.name),
-            template: Template::Scope,
+            template: Template::new("scope"),
             pagination: None,
         }
     }
@@ -570,12 +661,12 @@ impl Page for MaskPage {
     fn info(&self) -> PageInfo {
         PageInfo {
             short_title: self.name.clone(),
-            title: format!("Modifiers for Mask: {}", self.name),
+            title: self.dossier.string("mask_title", &[("name", &self.name)]),
             path: match self.page.total_pages {
                 1 => format!("modifiers/{}", self.name),
                 _ => Self::page_url(&self, self.page.current_page),
             },
-            template: Template::Mask,
+            template: Template::new("mask"),
             pagination: Some(self.page.clone()),
         }
     }
@@ -599,32 +690,31 @@ impl Page for MaskPage {
             name: String,
             display_name: Option<DocStringSer>,
             description: Option<DocStringSer>,
+            usages: Option<DocStringSer>,
         }
 
         let modifiers = self
             .modifiers
             .iter()
             .map(|m| self.dossier.entries.get(m).unwrap())
-            .map(|m| m.as_any().downcast_ref::<ScriptDocEntry>().unwrap())
             .map(|m| {
-                if let ScriptDocContent::Modifiers {
+                let ser = |s: DocString| DocStringSer(s, self.id, context.mapper.clone());
+                let display_name = m
+                    .properties(context, self.dossier.clone())
+                    .into_iter()
+                    .find(|(name, _)| name == "Display Name")
+                    .map(|(_, value)| ser(value));
+
+                Modifier {
+                    name: m.name().to_string(),
                     display_name,
-                    description,
-                    ..
-                } = m.content.as_ref().unwrap()
-                {
-                    return Modifier {
-                        name: m.name.clone(),
-                        display_name: display_name
-                            .as_ref()
-                            .map(|s| DocStringSer(s.clone(), self.id, context.mapper.clone())),
-                        description: description
-                            .as_ref()
-                            .map(|s| DocStringSer(s.clone(), self.id, context.mapper.clone())),
-                    };
+                    description: m.body().map(ser),
+                    usages: self
+                        .dossier
+                        .usages
+                        .count(m.id())
+                        .map(|c| ser(usage_count(&self.dossier, c))),
                 }
-
-                panic!("ScriptDocContent::Modifiers expected!");
             })
             .collect_vec();
 
@@ -653,3 +743,766 @@ impl Page for MaskPage {
         format!("modifiers/{}_p{}", self.name, page)
     }
 }
+
+pub struct UnusedReportPage {
+    dossier: Rc<Dossier>,
+}
+
+impl UnusedReportPage {
+    fn page_id() -> u64 {
+        util::hash(&"UNUSED_REPORT")
+    }
+}
+
+impl Page for UnusedReportPage {
+    fn id(&self) -> u64 {
+        Self::page_id()
+    }
+
+    fn group_id(&self) -> u64 {
+        Self::page_id()
+    }
+
+    fn info(&self) -> PageInfo {
+        PageInfo {
+            title: self.dossier.string("unused_in_vanilla", &[]),
+            short_title: self.dossier.string("unused_in_vanilla", &[]),
+            template: Template::new("report"),
+            path: "unused".into(),
+            pagination: None,
+        }
+    }
+
+    fn entries(&self) -> Vec<u64> {
+        vec![]
+    }
+
+    fn anchors(&self) -> Vec<(u64, String)> {
+        vec![]
+    }
+
+    fn data(&self, context: &PageContext) -> serde_json::Value {
+        #[derive(Serialize)]
+        struct Group {
+            name: String,
+            items: Vec<DocStringSer>,
+        }
+
+        #[derive(Serialize)]
+        struct Data {
+            body: DocStringSer,
+            groups: Vec<Group>,
+        }
+
+        let unused = self.dossier.usages.unused();
+        let groups = [
+            (ScriptReferenceKind::Effect, "effects"),
+            (ScriptReferenceKind::Trigger, "triggers"),
+            (ScriptReferenceKind::EventTarget, "event_targets"),
+            (ScriptReferenceKind::Modifier, "modifiers"),
+        ]
+        .into_iter()
+        .map(|(kind, name)| {
+            let mut entries = unused
+                .iter()
+                .filter(|(_, k)| *k == kind)
+                .filter_map(|(id, _)| self.dossier.entries.get(id))
+                .collect_vec();
+            entries.sort_by_key(|e| e.name());
+
+            Group {
+                name: self.dossier.string(name, &[]),
+                items: entries
+                    .iter()
+                    .map(|e| {
+                        DocStringSer(
+                            DocString::from(DocStringSegment::Link {
+                                contents: e.name().into(),
+                                url: context
+                                    .mapper
+                                    .borrow()
+                                    .page_to_entry_url(&self.id(), &e.id()),
+                            }),
+                            self.id(),
+                            context.mapper.clone(),
+                        )
+                    })
+                    .collect(),
+            }
+        })
+        .filter(|g| !g.items.is_empty())
+        .collect_vec();
+
+        serde_json::to_value(Data {
+            body: DocStringSer(
+                DocString::from(DocStringSegment::Text {
+                    contents: self.dossier.string("unused_report_body", &[]),
+                }),
+                self.id(),
+                context.mapper.clone(),
+            ),
+            groups,
+        })
+        .unwrap()
+    }
+
+    fn parent_id(&self) -> Option<u64> {
+        None
+    }
+
+    fn page_url(&self, _page: usize) -> String {
+        "unused".into()
+    }
+}
+
+/// Builds the report of documented entries that are never used in the game's script files.
+pub struct UnusedReportPageBuilder;
+
+impl PageBuilder for UnusedReportPageBuilder {
+    fn build_entries(&self, _dossier: &Dossier, _config: &Config) -> Vec<Box<dyn DocEntry>> {
+        vec![]
+    }
+
+    fn build_pages(&self, dossier: Rc<Dossier>, _config: &Config) -> Vec<Box<dyn Page>> {
+        vec![Box::new(UnusedReportPage { dossier })]
+    }
+}
+
+/// Formats the number of times an entry is used in the game's script files.
+fn usage_count(dossier: &Dossier, count: usize) -> DocString {
+    DocString::from(DocStringSegment::Text {
+        contents: match count {
+            0 => format!(
+                "<span class=\"pd-unused\">{}</span>",
+                html_escape(&dossier.string("unused_in_vanilla", &[]))
+            ),
+            _ => count.to_string(),
+        },
+    })
+}
+
+/// A changelog between the version of the game this profile documents and an older one.
+/// Without a category, this is the index listing how much changed in each category.
+pub struct ChangelogPage {
+    dossier: Rc<Dossier>,
+    diff: Rc<DossierDiff>,
+    category: Option<(String, String)>,
+}
+
+impl ChangelogPage {
+    /// Creates the changelog index and a page for each category with changes.
+    pub fn create(dossier: Rc<Dossier>, diff: DossierDiff) -> Vec<Box<dyn Page>> {
+        let diff = Rc::new(diff);
+        let categories = diff
+            .categories()
+            .into_iter()
+            .map(|(name, display_name)| (name.to_string(), display_name.to_string()))
+            .collect_vec();
+
+        std::iter::once(None)
+            .chain(categories.into_iter().map(Some))
+            .map(|category| {
+                Box::new(ChangelogPage {
+                    dossier: dossier.clone(),
+                    diff: diff.clone(),
+                    category,
+                }) as Box<dyn Page>
+            })
+            .collect()
+    }
+
+    fn page_id(category: Option<&str>) -> u64 {
+        match category {
+            Some(category) => util::hash(&format!("CHANGELOG_{}", category)),
+            None => util::hash(&"CHANGELOG_INDEX"),
+        }
+    }
+
+    /// Links to an entry in this profile if it still exists, or names it otherwise.
+    fn link(&self, context: &PageContext, name: &str, id: u64) -> DocStringSegment {
+        match self.dossier.entries.get(&id) {
+            Some(entry) if entry.name() == name => DocStringSegment::Link {
+                contents: name.to_owned(),
+                url: context.mapper.borrow().page_to_entry_url(&self.id(), &id),
+            },
+            _ => DocStringSegment::Text {
+                contents: name.to_owned(),
+            },
+        }
+    }
+
+    fn ser(&self, context: &PageContext, doc: DocString) -> DocStringSer {
+        DocStringSer(doc, self.id(), context.mapper.clone())
+    }
+}
+
+impl Page for ChangelogPage {
+    fn id(&self) -> u64 {
+        Self::page_id(self.category.as_ref().map(|(name, _)| name.as_str()))
+    }
+
+    fn group_id(&self) -> u64 {
+        self.id()
+    }
+
+    fn info(&self) -> PageInfo {
+        let (title, short_title, path) = match &self.category {
+            Some((name, display_name)) => (
+                self.dossier
+                    .string("changelog_title", &[("category", display_name)]),
+                display_name.clone(),
+                format!("changelog/{}", name),
+            ),
+            None => (
+                self.dossier.string("changelog", &[]),
+                self.dossier.string("changelog", &[]),
+                "changelog/index.html".into(),
+            ),
+        };
+
+        PageInfo {
+            title,
+            short_title,
+            template: match &self.category {
+                Some((name, _)) => Template::specialized("changelog", name),
+                None => Template::new("changelog"),
+            },
+            path,
+            pagination: None,
+        }
+    }
+
+    fn entries(&self) -> Vec<u64> {
+        vec![]
+    }
+
+    fn anchors(&self) -> Vec<(u64, String)> {
+        vec![]
+    }
+
+    fn data(&self, context: &PageContext) -> serde_json::Value {
+        #[derive(Serialize)]
+        struct CategorySummary {
+            name: String,
+            url: String,
+            added: usize,
+            removed: usize,
+            changed: usize,
+        }
+
+        #[derive(Serialize)]
+        struct Field {
+            name: String,
+            old: Option<DocStringSer>,
+            new: Option<DocStringSer>,
+            added_items: Vec<String>,
+            removed_items: Vec<String>,
+        }
+
+        #[derive(Serialize)]
+        struct Change {
+            name: DocStringSer,
+            fields: Vec<Field>,
+        }
+
+        #[derive(Serialize)]
+        struct Data {
+            old_version: String,
+            new_version: String,
+            categories: Vec<CategorySummary>,
+            added: Vec<Change>,
+            removed: Vec<Change>,
+            changed: Vec<Change>,
+        }
+
+        let count = |category: &str, kind: ChangeKind| {
+            self.diff
+                .changes_in(category)
+                .filter(|c| c.kind == kind)
+                .count()
+        };
+
+        let categories = match &self.category {
+            Some(_) => vec![],
+            None => self
+                .diff
+                .categories()
+                .into_iter()
+                .map(|(name, display_name)| CategorySummary {
+                    name: display_name.to_string(),
+                    url: context
+                        .mapper
+                        .borrow()
+                        .page_to_page_url(&self.id(), &Self::page_id(Some(name))),
+                    added: count(name, ChangeKind::Added),
+                    removed: count(name, ChangeKind::Removed),
+                    changed: count(name, ChangeKind::Changed),
+                })
+                .collect(),
+        };
+
+        let changes = |kind: ChangeKind| match &self.category {
+            Some((category, _)) => self
+                .diff
+                .changes_in(category)
+                .filter(|c| c.kind == kind)
+                .map(|c| Change {
+                    name: self.ser(context, self.link(context, &c.name, c.id).into()),
+                    fields: c
+                        .fields
+                        .iter()
+                        .map(|f| {
+                            let value = |v: &Option<RecordValue>| {
+                                v.as_ref().map(|v| {
+                                    self.ser(
+                                        context,
+                                        v.to_doc_string(|name, id| self.link(context, name, id)),
+                                    )
+                                })
+                            };
+                            let (added_items, removed_items) = f.list_changes().unwrap_or_default();
+
+                            Field {
+                                name: f.name.clone(),
+                                old: value(&f.old),
+                                new: value(&f.new),
+                                added_items: added_items.into_iter().map(String::from).collect(),
+                                removed_items: removed_items
+                                    .into_iter()
+                                    .map(String::from)
+                                    .collect(),
+                            }
+                        })
+                        .collect(),
+                })
+                .collect(),
+            None => vec![],
+        };
+
+        serde_json::to_value(Data {
+            old_version: self.diff.old_version.clone(),
+            new_version: self.diff.new_version.clone(),
+            categories,
+            added: changes(ChangeKind::Added),
+            removed: changes(ChangeKind::Removed),
+            changed: changes(ChangeKind::Changed),
+        })
+        .unwrap()
+    }
+
+    fn parent_id(&self) -> Option<u64> {
+        match self.category {
+            Some(_) => Some(Self::page_id(None)),
+            None => None,
+        }
+    }
+
+    fn page_url(&self, _page: usize) -> String {
+        self.info().path
+    }
+}
+
+/// Lists the entries that have been removed from a category, as they were before their removal.
+pub struct TombstonePage {
+    dossier: Rc<Dossier>,
+    category: String,
+    category_display_name: String,
+    entries: Vec<u64>,
+}
+
+impl TombstonePage {
+    /// Creates a page for each category that's had entries removed.
+    pub fn create(dossier: Rc<Dossier>, history: &HistoryIndex) -> Vec<Box<dyn Page>> {
+        history
+            .removed()
+            .iter()
+            .chunk_by(|r| {
+                (
+                    r.record.category.clone(),
+                    r.record.category_display_name.clone(),
+                )
+            })
+            .into_iter()
+            .map(|((category, category_display_name), removed)| {
+                Box::new(TombstonePage {
+                    dossier: dossier.clone(),
+                    category,
+                    category_display_name,
+                    entries: removed.map(|r| r.record.id).collect(),
+                }) as Box<dyn Page>
+            })
+            .collect()
+    }
+
+    pub fn page_id(category: &str) -> u64 {
+        util::hash(&format!("TOMBSTONE_{}", category))
+    }
+
+    fn history(&self) -> &HistoryIndex {
+        self.dossier.history.get().unwrap()
+    }
+}
+
+impl Page for TombstonePage {
+    fn id(&self) -> u64 {
+        Self::page_id(&self.category)
+    }
+
+    fn group_id(&self) -> u64 {
+        self.id()
+    }
+
+    fn info(&self) -> PageInfo {
+        let title = self.dossier.string(
+            "removed_category",
+            &[("category", &self.category_display_name)],
+        );
+        PageInfo {
+            title: title.clone(),
+            short_title: title,
+            template: Template::specialized("tombstone", &self.category),
+            path: format!("removed/{}", self.category),
+            pagination: None,
+        }
+    }
+
+    fn entries(&self) -> Vec<u64> {
+        self.entries.clone()
+    }
+
+    fn anchors(&self) -> Vec<(u64, String)> {
+        self.history()
+            .removed_in(&self.category)
+            .map(|r| (r.record.id, r.record.name.clone()))
+            .collect()
+    }
+
+    fn data(&self, context: &PageContext) -> serde_json::Value {
+        #[derive(Serialize)]
+        struct Property {
+            name: String,
+            value: DocStringSer,
+        }
+
+        #[derive(Serialize)]
+        struct Entry {
+            anchor: String,
+            name: String,
+            introduced_in: String,
+            removed_in: String,
+            body: Option<DocStringSer>,
+            properties: Vec<Property>,
+        }
+
+        #[derive(Serialize)]
+        struct Data {
+            entries: Vec<Entry>,
+        }
+
+        let ser = |doc: DocString| DocStringSer(doc, self.id(), context.mapper.clone());
+        // links go to entries that still exist, and everything else is left as text
+        let link = |name: &str, id: u64| match self.dossier.entries.get(&id) {
+            Some(entry) if entry.name() == name => DocStringSegment::Link {
+                contents: name.to_owned(),
+                url: context.mapper.borrow().page_to_entry_url(&self.id(), &id),
+            },
+            _ => DocStringSegment::Text {
+                contents: name.to_owned(),
+            },
+        };
+
+        let entries = self
+            .history()
+            .removed_in(&self.category)
+            .map(|r| Entry {
+                anchor: r.record.name.clone(),
+                name: r.record.name.clone(),
+                introduced_in: r.introduced_in.clone(),
+                removed_in: r.removed_in.clone(),
+                body: r.record.body.as_ref().map(|b| ser(b.to_doc_string(link))),
+                properties: r
+                    .record
+                    .properties
+                    .iter()
+                    .map(|(name, value)| Property {
+                        name: name.clone(),
+                        value: ser(value.to_doc_string(link)),
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        serde_json::to_value(Data { entries }).unwrap()
+    }
+
+    fn parent_id(&self) -> Option<u64> {
+        None
+    }
+
+    fn page_url(&self, _page: usize) -> String {
+        self.info().path
+    }
+}
+
+/// A link from a profile's landing page to the first page of a part of the site.
+struct LandingLink {
+    page_id: u64,
+    title: String,
+    /// The number of entries across every page the link leads to.
+    entries: usize,
+}
+
+/// The front page of a profile, which is its `index.html`, describing the game and linking to
+/// every category and every other part of the site.
+pub struct LandingPage {
+    dossier: Rc<Dossier>,
+    categories: Vec<LandingLink>,
+    sections: Vec<LandingLink>,
+}
+
+impl LandingPage {
+    pub fn page_id() -> u64 {
+        util::hash(&"LANDING")
+    }
+
+    /// Creates the landing page for the rest of a profile's pages.
+    pub fn new(dossier: Rc<Dossier>, pages: &[Box<dyn Page>]) -> LandingPage {
+        let mut categories = vec![];
+        let mut sections = vec![];
+
+        for page in pages {
+            let info = page.info();
+            let is_first_page = info
+                .pagination
+                .as_ref()
+                .map_or(true, |p| p.current_page == 1);
+            if page.parent_id().is_some() || !is_first_page {
+                continue;
+            }
+
+            let link = LandingLink {
+                page_id: page.id(),
+                title: info.short_title.clone(),
+                entries: pages
+                    .iter()
+                    .filter(|p| p.group_id() == page.group_id())
+                    .map(|p| p.entries().len())
+                    .sum(),
+            };
+
+            match info.template.fallbacks().last() {
+                Some("category_list") => categories.push(link),
+                // removed entries are linked to from their category's page instead
+                Some("tombstone") => {}
+                _ => sections.push(link),
+            }
+        }
+
+        categories.sort_by(|a, b| a.title.cmp(&b.title));
+        sections.sort_by(|a, b| a.title.cmp(&b.title));
+
+        LandingPage {
+            dossier,
+            categories,
+            sections,
+        }
+    }
+}
+
+impl Page for LandingPage {
+    fn id(&self) -> u64 {
+        Self::page_id()
+    }
+
+    fn group_id(&self) -> u64 {
+        Self::page_id()
+    }
+
+    fn info(&self) -> PageInfo {
+        PageInfo {
+            title: self.dossier.string("overview", &[]),
+            short_title: self.dossier.info.title().into(),
+            template: Template::new("landing"),
+            path: "index.html".into(),
+            pagination: None,
+        }
+    }
+
+    fn entries(&self) -> Vec<u64> {
+        vec![]
+    }
+
+    fn anchors(&self) -> Vec<(u64, String)> {
+        vec![]
+    }
+
+    fn data(&self, context: &PageContext) -> serde_json::Value {
+        #[derive(Serialize)]
+        struct Link {
+            name: String,
+            url: String,
+            entries: usize,
+        }
+
+        #[derive(Serialize)]
+        struct Data<'a> {
+            info: &'a DocInfo,
+            categories: Vec<Link>,
+            sections: Vec<Link>,
+        }
+
+        let mapper = context.mapper.borrow();
+        let links = |links: &[LandingLink]| {
+            links
+                .iter()
+                .map(|l| Link {
+                    name: l.title.clone(),
+                    url: mapper.page_to_page_url(&self.id(), &l.page_id),
+                    entries: l.entries,
+                })
+                .collect_vec()
+        };
+
+        serde_json::to_value(Data {
+            info: &self.dossier.info,
+            categories: links(&self.categories),
+            sections: links(&self.sections),
+        })
+        .unwrap()
+    }
+
+    fn parent_id(&self) -> Option<u64> {
+        None
+    }
+
+    fn page_url(&self, _page: usize) -> String {
+        self.info().path
+    }
+}
+
+/// A page of its own for a single entry, so that links to it don't break as entries move between
+/// the pages of their category.
+pub struct EntryPage {
+    dossier: Rc<Dossier>,
+    category: DocCategory,
+    entry: u64,
+    /// The page of the category the entry is listed on.
+    parent_id: u64,
+    file_name: String,
+}
+
+impl EntryPage {
+    pub fn new(
+        dossier: Rc<Dossier>,
+        category: &DocCategory,
+        entry: u64,
+        parent_id: u64,
+        file_name: String,
+    ) -> EntryPage {
+        EntryPage {
+            dossier,
+            category: category.clone(),
+            entry,
+            parent_id,
+            file_name,
+        }
+    }
+
+    /// Picks a page name for each entry in a category, by entry ID. Names only keep characters
+    /// that are safe in a URL, so different entries can end up with the same name, including
+    /// names that only differ in case, which clash on case-insensitive filesystems. Entries whose
+    /// names are already safe get them first, then any others get a hash of their own name added.
+    pub fn file_names<'a>(entries: impl Iterator<Item = (u64, &'a str)>) -> HashMap<u64, String> {
+        let mut taken = HashSet::new();
+        let mut names = HashMap::new();
+        let entries = entries.sorted_by_key(|(_, name)| Self::file_name(name) != *name);
+        for (id, name) in entries {
+            let base = Self::file_name(name);
+            let mut file_name = base.clone();
+            let mut attempt = 0usize;
+            while !taken.insert(file_name.to_lowercase()) {
+                file_name = format!("{}_{:08x}", base, util::hash(&(name, attempt)) as u32);
+                attempt += 1;
+            }
+
+            names.insert(id, file_name);
+        }
+
+        names
+    }
+
+    fn name(&self) -> &str {
+        self.dossier.entries.get(&self.entry).unwrap().name()
+    }
+
+    /// The name of an entry's page, which keeps to characters that are safe in a URL.
+    fn file_name(name: &str) -> String {
+        name.chars()
+            .map(
+                |c| match c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                    true => c,
+                    false => '_',
+                },
+            )
+            .collect()
+    }
+}
+
+impl Page for EntryPage {
+    fn id(&self) -> u64 {
+        util::hash(&("ENTRY", self.entry))
+    }
+
+    fn group_id(&self) -> u64 {
+        self.id()
+    }
+
+    fn info(&self) -> PageInfo {
+        PageInfo {
+            title: self.name().to_owned(),
+            short_title: self.name().to_owned(),
+            template: Template::specialized("entry", &self.category.name),
+            path: format!("{}/{}", self.category.name, self.file_name),
+            pagination: None,
+        }
+    }
+
+    fn entries(&self) -> Vec<u64> {
+        vec![self.entry]
+    }
+
+    fn anchors(&self) -> Vec<(u64, String)> {
+        vec![(self.entry, self.name().to_owned())]
+    }
+
+    fn data(&self, context: &PageContext) -> serde_json::Value {
+        #[derive(Serialize)]
+        struct Data<'a> {
+            category: &'a str,
+            category_url: String,
+            entry: EntryData,
+        }
+
+        serde_json::to_value(Data {
+            category: &self.category.display_name,
+            category_url: context
+                .mapper
+                .borrow()
+                .page_to_page_url(&self.id(), &self.parent_id),
+            entry: EntryData::new(&self.dossier, self.entry, self.id(), context),
+        })
+        .unwrap()
+    }
+
+    fn parent_id(&self) -> Option<u64> {
+        Some(self.parent_id)
+    }
+
+    fn page_url(&self, _page: usize) -> String {
+        self.info().path
+    }
+
+    fn in_site_map(&self) -> bool {
+        false
+    }
+}
@@ -0,0 +1,190 @@
+use std::rc::Rc;
+
+use anyhow::Result;
+use clauser::data::script_doc_parser::{
+    doc_string::{DocString, DocStringSegment},
+    ScriptDocCategory, ScriptDocEntry,
+};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    dossier::Dossier,
+    entry::DocEntry,
+    page::{GenericListPage, MaskPage, PageContext, ScopePage, ENTRY_URL_PREFIX},
+    util::DocStringSer,
+};
+
+/// A piece of a recorded value.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RecordSegment {
+    Text {
+        html: String,
+    },
+    /// Code, already highlighted and rendered to HTML.
+    Code {
+        html: String,
+    },
+    /// A link to another entry in the same dossier.
+    Link {
+        name: String,
+        id: u64,
+    },
+}
+
+/// A `DocString` with its links resolved to entries rather than URLs, so that it can be compared
+/// between dossiers and saved to disk.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, Default)]
+pub struct RecordValue(pub Vec<RecordSegment>);
+
+impl RecordValue {
+    pub fn from_doc_string(doc: &DocString) -> Result<RecordValue> {
+        let mut segments = Vec::new();
+        for segment in doc.segments() {
+            segments.push(match segment {
+                DocStringSegment::Link { contents, url } if url.starts_with(ENTRY_URL_PREFIX) => {
+                    RecordSegment::Link {
+                        name: contents.clone(),
+                        id: url[ENTRY_URL_PREFIX.len()..].parse()?,
+                    }
+                }
+                DocStringSegment::Code { .. } | DocStringSegment::RawCode { .. } => {
+                    RecordSegment::Code {
+                        html: DocStringSer::segment_html(segment)?,
+                    }
+                }
+                _ => RecordSegment::Text {
+                    html: DocStringSer::segment_html(segment)?,
+                },
+            });
+        }
+
+        Ok(RecordValue(segments))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The value as text for comparisons, where links are compared by name.
+    pub fn text(&self) -> String {
+        self.0
+            .iter()
+            .map(|s| match s {
+                RecordSegment::Text { html } | RecordSegment::Code { html } => html.as_str(),
+                RecordSegment::Link { name, .. } => name.as_str(),
+            })
+            .join("")
+    }
+
+    /// If this value is only a list of links, like a list of scopes, returns their names.
+    pub fn link_names(&self) -> Option<Vec<&str>> {
+        let mut names = Vec::new();
+        for segment in &self.0 {
+            match segment {
+                RecordSegment::Link { name, .. } => names.push(name.as_str()),
+                RecordSegment::Text { html }
+                    if html.chars().all(|c| c == ',' || c.is_whitespace()) => {}
+                _ => return None,
+            }
+        }
+
+        Some(names)
+    }
+
+    /// Turns this value back into a `DocString`, using `link` to turn each link into a segment.
+    pub fn to_doc_string<F>(&self, link: F) -> DocString
+    where
+        F: Fn(&str, u64) -> DocStringSegment,
+    {
+        DocString::new_from_iter(
+            self.0.iter().map(|s| match s {
+                RecordSegment::Text { html } | RecordSegment::Code { html } => {
+                    DocStringSegment::Text {
+                        contents: html.clone(),
+                    }
+                }
+                RecordSegment::Link { name, id } => link(name, *id),
+            }),
+            None,
+        )
+    }
+}
+
+/// Everything an entry says about itself, independent of how it's rendered.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct EntryRecord {
+    pub id: u64,
+    /// The name of the category the entry belongs to, like `effects`.
+    pub category: String,
+    pub category_display_name: String,
+    pub name: String,
+    pub body: Option<RecordValue>,
+    pub properties: Vec<(String, RecordValue)>,
+}
+
+impl EntryRecord {
+    pub fn new(
+        entry: &dyn DocEntry,
+        dossier: Rc<Dossier>,
+        context: &PageContext,
+    ) -> Result<Option<EntryRecord>> {
+        let Some((category, category_display_name)) = Self::category_of(entry, &dossier) else {
+            return Ok(None);
+        };
+
+        let body = match entry.body() {
+            Some(body) => Some(RecordValue::from_doc_string(&body)?),
+            None => None,
+        };
+
+        let mut properties = Vec::new();
+        for (name, value) in entry.properties(context, dossier) {
+            properties.push((name, RecordValue::from_doc_string(&value)?));
+        }
+
+        Ok(Some(EntryRecord {
+            id: entry.id(),
+            category,
+            category_display_name,
+            name: entry.name().to_string(),
+            body,
+            properties,
+        }))
+    }
+
+    /// Records every entry in a dossier, sorted by category and name.
+    pub fn record_all(dossier: Rc<Dossier>) -> Result<Vec<EntryRecord>> {
+        let context = dossier.recording_context();
+        let mut records = Vec::new();
+        for entry in dossier.entries.values() {
+            if let Some(record) = Self::new(entry.as_ref(), dossier.clone(), &context)? {
+                records.push(record);
+            }
+        }
+
+        records.sort_by(|a, b| a.category.cmp(&b.category).then(a.name.cmp(&b.name)));
+        Ok(records)
+    }
+
+    /// The name and display name of the category an entry is listed under, if any.
+    fn category_of(entry: &dyn DocEntry, dossier: &Dossier) -> Option<(String, String)> {
+        let names = |name: &str, display_name: &str| Some((name.into(), display_name.into()));
+        match entry.category_id() {
+            Some(id) => match dossier.category(id) {
+                Some(category) => Some((category.name.clone(), category.display_name.clone())),
+                None if id == ScopePage::category_id() => names("scopes", "Scopes"),
+                None if id == MaskPage::category_id() => names("masks", "Masks"),
+                None => None,
+            },
+            // modifiers are listed on their mask's page instead of in a category
+            None => match entry.as_any().downcast_ref::<ScriptDocEntry>() {
+                Some(e) if e.category == ScriptDocCategory::Modifiers => {
+                    names("modifiers", "Modifiers")
+                }
+                _ => None,
+            },
+        }
+    }
+}
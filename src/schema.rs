@@ -0,0 +1,202 @@
+use std::collections::BTreeMap;
+
+use itertools::Itertools;
+use serde::Serialize;
+use serde_json::Value;
+
+/// How many items of each list are kept in the samples, so they stay readable.
+const SAMPLE_LIST_LENGTH: usize = 2;
+
+/// The fields every template is rendered with, alongside `data`.
+const PAGE_FIELDS: [(&str, &str); 9] = [
+    (
+        "title",
+        "The title of the page, followed by the title of its profile. Meant for <title>.",
+    ),
+    ("name", "The title of the page on its own."),
+    (
+        "data",
+        "The data specific to the page's template. See `templates` for a sample of each.",
+    ),
+    (
+        "breadcrumbs",
+        "The pages above this one. Pass to the `breadcrumbs` helper.",
+    ),
+    (
+        "page_id",
+        "The ID of the page, which the helpers use to work out relative URLs.",
+    ),
+    (
+        "site_map",
+        "Every page in the profile, as a tree. Pass to the `site_map` helper.",
+    ),
+    (
+        "doc_info",
+        "The game the documentation was generated from, and the version of pdxdoc.",
+    ),
+    (
+        "versions",
+        "The other versions of the site this page can be found in, each with a `name`, a `url` \
+         (missing if the page doesn't exist in that version) and `is_current`.",
+    ),
+    (
+        "theme",
+        "The theme's settings from theme.json, with any overrides from the config.",
+    ),
+];
+
+#[derive(Serialize)]
+struct HelperSchema {
+    usage: &'static str,
+    description: &'static str,
+    /// The names available inside the helper's block, with what they hold.
+    block_params: BTreeMap<&'static str, &'static str>,
+}
+
+impl HelperSchema {
+    fn new(
+        usage: &'static str,
+        description: &'static str,
+        block_params: &[(&'static str, &'static str)],
+    ) -> HelperSchema {
+        HelperSchema {
+            usage,
+            description,
+            block_params: block_params.iter().cloned().collect(),
+        }
+    }
+}
+
+/// A description of everything templates are rendered with, for theme authors.
+#[derive(Serialize)]
+pub struct ContextSchema {
+    /// The fields available to every template, with what they hold.
+    page: BTreeMap<&'static str, &'static str>,
+    /// A sample of the data each template is rendered with, by template name.
+    templates: BTreeMap<String, Value>,
+    /// The block helpers templates can use.
+    helpers: BTreeMap<&'static str, HelperSchema>,
+}
+
+impl ContextSchema {
+    /// Describes the template context, using samples from `SiteGenerator::sample_data`.
+    pub fn new(samples: BTreeMap<String, Value>) -> ContextSchema {
+        let templates = samples
+            .into_iter()
+            .map(|(name, mut sample)| {
+                Self::shorten(&mut sample);
+                (name, sample)
+            })
+            .collect();
+
+        let helpers = BTreeMap::from([
+            (
+                "breadcrumbs",
+                HelperSchema::new(
+                    "{{#breadcrumbs breadcrumbs}}...{{/breadcrumbs}}",
+                    "Renders the block once for each page above this one, from the top down.",
+                    &[
+                        ("title", "The title of the page."),
+                        ("url", "The URL of the page, relative to this one."),
+                        ("is_first", "Whether this is the first crumb."),
+                        (
+                            "is_last",
+                            "Whether this is the last crumb, the current page.",
+                        ),
+                        ("is_paged", "Whether the page is split into several pages."),
+                        (
+                            "current_page",
+                            "The number of the current page, if `is_paged` is set.",
+                        ),
+                        ("total_pages", "The number of pages, if `is_paged` is set."),
+                    ],
+                ),
+            ),
+            (
+                "pagination",
+                HelperSchema::new(
+                    "{{#pagination pagination 2}}...{{/pagination}}",
+                    "Renders the block once if the page is split into several pages. The second \
+                     parameter is how many pages to list either side of the current one.",
+                    &[
+                        (
+                            "first_page",
+                            "1, unless this is the first page, in which case it's missing.",
+                        ),
+                        (
+                            "pages_before",
+                            "The numbers of the pages just before this one.",
+                        ),
+                        ("current_page", "The number of this page."),
+                        (
+                            "pages_after",
+                            "The numbers of the pages just after this one.",
+                        ),
+                        (
+                            "last_page",
+                            "The number of the last page, unless this is the last page.",
+                        ),
+                    ],
+                ),
+            ),
+            (
+                "site_map",
+                HelperSchema::new(
+                    "{{#site_map site_map}}...{{/site_map}}",
+                    "Renders the block once for each page in the site map, depth first, with \
+                     children sorted by title.",
+                    &[
+                        ("title", "The title of the page."),
+                        ("url", "The URL of the page, relative to this one."),
+                        (
+                            "depth",
+                            "How deep the page is in the site map, starting at 0.",
+                        ),
+                        ("has_children", "Whether the page has pages below it."),
+                        ("is_current", "Whether the page is the one being rendered."),
+                    ],
+                ),
+            ),
+            (
+                "columns",
+                HelperSchema::new(
+                    "{{#columns items 2}}...{{/columns}}",
+                    "Splits a list into columns, rendering the block once for each column.",
+                    &[
+                        ("values", "The items in the column."),
+                        ("n", "The number of columns."),
+                    ],
+                ),
+            ),
+        ]);
+
+        ContextSchema {
+            page: PAGE_FIELDS.into_iter().collect(),
+            templates,
+            helpers,
+        }
+    }
+
+    /// Cuts every list in a sample down to its first few items.
+    fn shorten(value: &mut Value) {
+        match value {
+            Value::Array(values) => {
+                values.truncate(SAMPLE_LIST_LENGTH);
+                values.iter_mut().for_each(Self::shorten);
+            }
+            Value::Object(values) => {
+                // the children in the site map are keyed by name rather than listed
+                if let Some(Value::Object(children)) = values.get_mut("children") {
+                    let keep = children
+                        .keys()
+                        .take(SAMPLE_LIST_LENGTH)
+                        .cloned()
+                        .collect_vec();
+                    children.retain(|k, _| keep.contains(k));
+                }
+                values.values_mut().for_each(Self::shorten);
+            }
+            _ => {}
+        }
+    }
+}
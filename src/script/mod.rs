@@ -0,0 +1,444 @@
+//! A reader for the game's script files that keeps where everything came from.
+//!
+//! clauser deserializes script into values, which loses the lines statements are on and the
+//! comments above them. Usage examples, lint reports and doc comments on mod definitions all need
+//! those, so script files are read with the small tokenizer and parser here instead.
+
+use std::{
+    fs,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Error, Result};
+use itertools::Itertools;
+
+mod vocabulary;
+
+pub use vocabulary::{ScriptContext, ScriptReference, ScriptReferenceKind, ScriptVocabulary};
+
+/// The value on the right hand side of a script statement.
+#[derive(Debug, Clone)]
+pub enum ScriptValue {
+    Scalar(String),
+    Block(Vec<ScriptNode>),
+}
+
+/// A single statement in a script file, like `key = value` or `key = { ... }`.
+/// Bare values inside a block (like the event names in `events = { a.1 a.2 }`) have no key.
+#[derive(Debug, Clone)]
+pub struct ScriptNode {
+    pub key: Option<String>,
+    pub operator: Option<String>,
+    pub value: ScriptValue,
+    /// The (1-based) line this statement starts on.
+    pub line: usize,
+    /// The (1-based) line this statement ends on.
+    pub end_line: usize,
+    /// Where this statement is in the source of its file, in bytes.
+    pub span: Range<usize>,
+    /// Comment lines directly above this statement, without the leading `#`.
+    pub comments: Vec<String>,
+}
+
+impl ScriptNode {
+    pub fn key(&self) -> &str {
+        self.key.as_deref().unwrap_or("")
+    }
+
+    pub fn scalar(&self) -> Option<&str> {
+        match &self.value {
+            ScriptValue::Scalar(s) => Some(s.as_str()),
+            ScriptValue::Block(_) => None,
+        }
+    }
+
+    pub fn children(&self) -> &[ScriptNode] {
+        match &self.value {
+            ScriptValue::Block(children) => children.as_slice(),
+            ScriptValue::Scalar(_) => &[],
+        }
+    }
+
+    pub fn is_block(&self) -> bool {
+        matches!(self.value, ScriptValue::Block(_))
+    }
+
+    /// Returns the first child with the given key.
+    pub fn get(&self, key: &str) -> Option<&ScriptNode> {
+        self.children().iter().find(|c| c.key() == key)
+    }
+
+    /// Returns the scalar value of the first child with the given key.
+    pub fn get_scalar(&self, key: &str) -> Option<&str> {
+        self.get(key).and_then(|c| c.scalar())
+    }
+}
+
+/// A parsed script file.
+pub struct ScriptFile {
+    /// The path of this file relative to the game (or mod) root, with forward slashes.
+    pub path: String,
+    pub nodes: Vec<ScriptNode>,
+    source: String,
+}
+
+impl ScriptFile {
+    pub fn read(disk_path: &Path, path: &str) -> Result<ScriptFile> {
+        let bytes = fs::read(disk_path)?;
+        let text = String::from_utf8_lossy(&bytes);
+        ScriptFile::parse(path, &text)
+    }
+
+    pub fn parse(path: &str, text: &str) -> Result<ScriptFile> {
+        let source = text.trim_start_matches('\u{feff}').to_string();
+        let tokens = Tokenizer::new(&source)
+            .tokenize()
+            .map_err(|(line, msg)| Error::msg(format!("{}:{}: {}", path, line, msg)))?;
+
+        let mut parser = Parser { tokens, pos: 0 };
+        let nodes = parser.parse_block(path, 0)?;
+
+        Ok(ScriptFile {
+            path: path.to_string(),
+            nodes,
+            source,
+        })
+    }
+
+    /// Returns the source text of a node in this file, with common indentation removed. Only the
+    /// node itself is included, even if it shares its lines with other statements.
+    pub fn snippet(&self, node: &ScriptNode) -> String {
+        let lines = self.source[node.span.clone()]
+            .lines()
+            .map(|l| l.trim_end())
+            .collect_vec();
+
+        let indent = lines
+            .iter()
+            .skip(1)
+            .filter(|l| !l.trim().is_empty())
+            .map(|l| l.len() - l.trim_start().len())
+            .min()
+            .unwrap_or(0);
+
+        lines
+            .iter()
+            .enumerate()
+            .map(|(i, l)| match i {
+                0 => l.trim_start(),
+                _ => l.get(indent..).unwrap_or(l.trim_start()),
+            })
+            .join("\n")
+    }
+}
+
+/// Collects all files under `root` matching `glob`, sorted by path so that load order is stable.
+pub fn find_script_files(root: &Path, glob: &str) -> Result<Vec<(PathBuf, String)>> {
+    if !root.is_dir() {
+        return Ok(vec![]);
+    }
+
+    let glob = wax::Glob::new(glob).map_err(|e| Error::msg(format!("{}", e)))?;
+    let mut files = Vec::new();
+    for entry in glob.walk(root) {
+        let entry = match entry.map_err(std::io::Error::from) {
+            Ok(entry) => entry,
+            // mods only have the directories they change
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(Error::msg(format!("{}", e))),
+        };
+        let relative = entry
+            .path()
+            .strip_prefix(root)?
+            .to_str()
+            .unwrap()
+            .replace("\\", "/");
+        files.push((entry.path().to_path_buf(), relative));
+    }
+
+    files.sort_by(|(_, a), (_, b)| a.cmp(b));
+    Ok(files)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    Word(String),
+    Operator(String),
+    Open,
+    Close,
+    Comment(String),
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    line: usize,
+    /// Where the token is in the source, in bytes.
+    span: Range<usize>,
+    /// Whether a blank line separates this token from the previous one.
+    after_blank: bool,
+}
+
+struct Tokenizer<'a> {
+    chars: std::iter::Peekable<std::str::CharIndices<'a>>,
+    len: usize,
+    line: usize,
+}
+
+impl<'a> Tokenizer<'a> {
+    fn new(text: &'a str) -> Tokenizer<'a> {
+        Tokenizer {
+            chars: text.char_indices().peekable(),
+            len: text.len(),
+            line: 1,
+        }
+    }
+
+    fn tokenize(mut self) -> Result<Vec<Token>, (usize, String)> {
+        let mut tokens = Vec::new();
+        let mut newlines = 0;
+
+        while let Some((start, c)) = self.chars.next() {
+            let line = self.line;
+            let kind = match c {
+                '\n' => {
+                    self.line += 1;
+                    newlines += 1;
+                    continue;
+                }
+                c if c.is_whitespace() => continue,
+                '{' => TokenKind::Open,
+                '}' => TokenKind::Close,
+                '#' => {
+                    let mut comment = String::new();
+                    while let Some((_, c)) = self.chars.next_if(|(_, c)| *c != '\n') {
+                        comment.push(c);
+                    }
+                    TokenKind::Comment(comment.trim_end().to_string())
+                }
+                '"' => {
+                    let mut s = String::new();
+                    loop {
+                        match self.chars.next().map(|(_, c)| c) {
+                            Some('"') => break,
+                            Some('\\') => {
+                                if let Some((_, escaped)) = self.chars.next() {
+                                    s.push(escaped);
+                                }
+                            }
+                            Some('\n') => {
+                                self.line += 1;
+                                s.push('\n');
+                            }
+                            Some(c) => s.push(c),
+                            None => return Err((line, "unterminated string".into())),
+                        }
+                    }
+                    TokenKind::Word(s)
+                }
+                '=' | '<' | '>' | '!' | '?' => {
+                    let mut op = c.to_string();
+                    if let Some((_, c)) = self.chars.next_if(|(_, c)| *c == '=') {
+                        op.push(c);
+                    }
+                    TokenKind::Operator(op)
+                }
+                '@' if self.chars.peek().map(|(_, c)| *c) == Some('[') => {
+                    // inline math, like @[ x + 1 ]
+                    let mut s = String::from("@");
+                    while let Some((_, c)) = self.chars.next() {
+                        s.push(c);
+                        if c == ']' {
+                            break;
+                        }
+                    }
+                    TokenKind::Word(s)
+                }
+                c => {
+                    let mut s = c.to_string();
+                    while let Some((_, c)) = self.chars.next_if(|(_, c)| {
+                        !c.is_whitespace()
+                            && !matches!(c, '{' | '}' | '=' | '<' | '>' | '!' | '?' | '#' | '"')
+                    }) {
+                        s.push(c);
+                    }
+                    TokenKind::Word(s)
+                }
+            };
+
+            let end = self.chars.peek().map(|(i, _)| *i).unwrap_or(self.len);
+            tokens.push(Token {
+                kind,
+                line,
+                span: start..end,
+                after_blank: newlines > 1,
+            });
+            newlines = 0;
+        }
+
+        Ok(tokens)
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    /// Parses statements until the end of the current block. `open_line` is the line the block was
+    /// opened on, or 0 for the top level of the file.
+    fn parse_block(&mut self, path: &str, open_line: usize) -> Result<Vec<ScriptNode>> {
+        let mut nodes = Vec::new();
+        let mut comments: Vec<String> = Vec::new();
+        let mut last_comment_line = 0;
+
+        while let Some(token) = self.next() {
+            if token.after_blank {
+                comments.clear();
+            }
+
+            let start = token.span.start;
+
+            let (key, line) = match token.kind {
+                TokenKind::Comment(comment) => {
+                    // a comment on the same line as the previous statement doesn't belong to the next one
+                    let trailing = token.line == open_line
+                        || nodes
+                            .last()
+                            .map(|n: &ScriptNode| n.end_line == token.line)
+                            .unwrap_or(false);
+                    if !trailing {
+                        if last_comment_line + 1 != token.line {
+                            comments.clear();
+                        }
+                        comments.push(comment);
+                        last_comment_line = token.line;
+                    }
+                    continue;
+                }
+                TokenKind::Close if open_line == 0 => {
+                    // vanilla files occasionally have stray closing braces, ignore them like the game does
+                    continue;
+                }
+                TokenKind::Close => return Ok(nodes),
+                TokenKind::Open => {
+                    let children = self.parse_block(path, token.line)?;
+                    nodes.push(ScriptNode {
+                        key: None,
+                        operator: None,
+                        value: ScriptValue::Block(children),
+                        line: token.line,
+                        end_line: self.last_line(),
+                        span: start..self.last_end(),
+                        comments: Vec::new(),
+                    });
+                    comments.clear();
+                    continue;
+                }
+                TokenKind::Operator(op) => {
+                    return Err(Error::msg(format!(
+                        "{}:{}: unexpected operator {}",
+                        path, token.line, op
+                    )))
+                }
+                TokenKind::Word(word) => (word, token.line),
+            };
+
+            let node_comments = match last_comment_line + 1 == line {
+                true => comments.drain(..).collect(),
+                false => Vec::new(),
+            };
+            comments.clear();
+
+            let operator = match self.peek().map(|t| &t.kind) {
+                Some(TokenKind::Operator(_)) => match self.next().unwrap().kind {
+                    TokenKind::Operator(op) => op,
+                    _ => unreachable!(),
+                },
+                _ => {
+                    // bare value
+                    nodes.push(ScriptNode {
+                        key: None,
+                        operator: None,
+                        value: ScriptValue::Scalar(key),
+                        line,
+                        end_line: line,
+                        span: start..self.last_end(),
+                        comments: node_comments,
+                    });
+                    continue;
+                }
+            };
+
+            let value = match self.next() {
+                Some(Token {
+                    kind: TokenKind::Open,
+                    line: open_line,
+                    ..
+                }) => ScriptValue::Block(self.parse_block(path, open_line)?),
+                Some(Token {
+                    kind: TokenKind::Word(word),
+                    ..
+                }) => {
+                    // tagged blocks like `color = rgb { 1 2 3 }`
+                    if let Some(Token {
+                        kind: TokenKind::Open,
+                        line: open_line,
+                        ..
+                    }) = self.peek().cloned()
+                    {
+                        self.next();
+                        ScriptValue::Block(self.parse_block(path, open_line)?)
+                    } else {
+                        ScriptValue::Scalar(word)
+                    }
+                }
+                _ => {
+                    return Err(Error::msg(format!(
+                        "{}:{}: expected a value for {}",
+                        path, line, key
+                    )))
+                }
+            };
+
+            nodes.push(ScriptNode {
+                key: Some(key),
+                operator: Some(operator),
+                value,
+                line,
+                end_line: self.last_line(),
+                span: start..self.last_end(),
+                comments: node_comments,
+            });
+        }
+
+        Ok(nodes)
+    }
+
+    fn last_line(&self) -> usize {
+        self.tokens
+            .get(self.pos.saturating_sub(1))
+            .map(|t| t.line)
+            .unwrap_or(1)
+    }
+
+    /// The end of the last token read, in bytes.
+    fn last_end(&self) -> usize {
+        self.tokens
+            .get(self.pos.saturating_sub(1))
+            .map(|t| t.span.end)
+            .unwrap_or(0)
+    }
+}
@@ -0,0 +1,292 @@
+use std::collections::HashMap;
+
+use clauser::data::script_doc_parser::{ScriptDocCategory, ScriptDocEntry};
+
+use crate::{
+    definitions::{DefinitionKind, ScriptDefinitionEntry},
+    dossier::Dossier,
+    entry::DocEntry,
+    modifier_types::ModifierTypeEntry,
+};
+
+use super::{ScriptFile, ScriptNode};
+
+/// Blocks whose contents are always evaluated as triggers.
+const TRIGGER_BLOCKS: [&str; 9] = [
+    "trigger",
+    "limit",
+    "is_shown",
+    "is_valid",
+    "possible",
+    "potential",
+    "allow",
+    "cancellation_trigger",
+    "alternative_limit",
+];
+
+/// Blocks whose contents are always executed as effects.
+const EFFECT_BLOCKS: [&str; 5] = ["effect", "immediate", "after", "option", "on_create"];
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ScriptContext {
+    Effect,
+    Trigger,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ScriptReferenceKind {
+    Effect,
+    Trigger,
+    EventTarget,
+    Modifier,
+}
+
+/// An effect, trigger, event target or modifier used somewhere in a script file.
+pub struct ScriptReference<'a> {
+    pub id: u64,
+    pub kind: ScriptReferenceKind,
+    pub context: ScriptContext,
+    pub node: &'a ScriptNode,
+}
+
+/// The set of script keywords the dossier knows about, indexed by name.
+pub struct ScriptVocabulary {
+    effects: HashMap<String, u64>,
+    triggers: HashMap<String, u64>,
+    event_targets: HashMap<String, u64>,
+    modifiers: HashMap<String, u64>,
+    script_values: HashMap<String, u64>,
+}
+
+impl ScriptVocabulary {
+    pub fn from_dossier(dossier: &Dossier) -> ScriptVocabulary {
+        let mut effects = HashMap::new();
+        let mut triggers = HashMap::new();
+        let mut event_targets = HashMap::new();
+        let mut modifiers = HashMap::new();
+
+        let mut definitions = Vec::new();
+        let mut modifier_types = Vec::new();
+
+        for entry in dossier.entries.values() {
+            if let Some(definition) = entry.as_any().downcast_ref::<ScriptDefinitionEntry>() {
+                definitions.push((definition.kind(), definition.name()));
+                continue;
+            }
+
+            if let Some(modifier_type) = entry.as_any().downcast_ref::<ModifierTypeEntry>() {
+                modifier_types.push((modifier_type.name(), modifier_type.id()));
+                continue;
+            }
+
+            let Some(entry) = entry.as_any().downcast_ref::<ScriptDocEntry>() else {
+                continue;
+            };
+
+            let table = match entry.category {
+                ScriptDocCategory::Effects => &mut effects,
+                ScriptDocCategory::Triggers => &mut triggers,
+                ScriptDocCategory::EventTargets => &mut event_targets,
+                ScriptDocCategory::Modifiers => &mut modifiers,
+                _ => continue,
+            };
+
+            table.insert(entry.name.clone(), entry.id);
+        }
+
+        let mut vocabulary = ScriptVocabulary {
+            effects,
+            triggers,
+            event_targets,
+            modifiers,
+            script_values: HashMap::new(),
+        };
+
+        for (kind, name) in definitions {
+            vocabulary.add_definition(kind, name);
+        }
+
+        // modifier types the script docs don't know about, like the ones mods add
+        for (name, id) in modifier_types {
+            vocabulary.modifiers.entry(name.to_string()).or_insert(id);
+        }
+
+        vocabulary
+    }
+
+    /// Adds a scripted effect, scripted trigger or script value defined in script, so that uses of
+    /// it are recognized.
+    pub fn add_definition(&mut self, kind: DefinitionKind, name: &str) {
+        let table = match kind {
+            DefinitionKind::ScriptedEffect => &mut self.effects,
+            DefinitionKind::ScriptedTrigger => &mut self.triggers,
+            DefinitionKind::ScriptValue => &mut self.script_values,
+            DefinitionKind::OnAction => return,
+        };
+
+        table.insert(name.to_string(), kind.entry_id_for_name(name));
+    }
+
+    /// Returns the names of every keyword of the given kind.
+    pub fn names(&self, kind: ScriptReferenceKind) -> impl Iterator<Item = &str> {
+        let table = match kind {
+            ScriptReferenceKind::Effect => &self.effects,
+            ScriptReferenceKind::Trigger => &self.triggers,
+            ScriptReferenceKind::EventTarget => &self.event_targets,
+            ScriptReferenceKind::Modifier => &self.modifiers,
+        };
+
+        table.keys().map(|k| k.as_str())
+    }
+
+    /// Returns the ID and kind of every keyword in this vocabulary.
+    pub fn ids(&self) -> impl Iterator<Item = (u64, ScriptReferenceKind)> + '_ {
+        let kinds = [
+            (&self.effects, ScriptReferenceKind::Effect),
+            (&self.triggers, ScriptReferenceKind::Trigger),
+            (&self.event_targets, ScriptReferenceKind::EventTarget),
+            (&self.modifiers, ScriptReferenceKind::Modifier),
+        ];
+
+        kinds
+            .into_iter()
+            .flat_map(|(table, kind)| table.values().map(move |id| (*id, kind)))
+    }
+
+    pub fn effect(&self, name: &str) -> Option<u64> {
+        self.effects.get(name).copied()
+    }
+
+    pub fn trigger(&self, name: &str) -> Option<u64> {
+        self.triggers.get(name).copied()
+    }
+
+    pub fn event_target(&self, name: &str) -> Option<u64> {
+        self.event_targets.get(name).copied()
+    }
+
+    pub fn modifier(&self, name: &str) -> Option<u64> {
+        self.modifiers.get(name).copied()
+    }
+
+    pub fn script_value(&self, name: &str) -> Option<u64> {
+        self.script_values.get(name).copied()
+    }
+
+    /// Walks a block of script in the given context, calling `visit` for every effect, trigger
+    /// or event target used in it.
+    pub fn walk<'a, F>(&self, nodes: &'a [ScriptNode], context: ScriptContext, visit: &mut F)
+    where
+        F: FnMut(ScriptReference<'a>),
+    {
+        for node in nodes {
+            let key = node.key();
+            let found = match context {
+                ScriptContext::Effect => {
+                    self.effect(key).map(|id| (id, ScriptReferenceKind::Effect))
+                }
+                ScriptContext::Trigger => self
+                    .trigger(key)
+                    .map(|id| (id, ScriptReferenceKind::Trigger)),
+            };
+
+            match found {
+                Some((id, kind)) => visit(ScriptReference {
+                    id,
+                    kind,
+                    context,
+                    node,
+                }),
+                None => {
+                    // links can be chained, like `owner.capital = { ... }`
+                    for link in key.split('.') {
+                        if let Some(id) = self.event_target(link) {
+                            visit(ScriptReference {
+                                id,
+                                kind: ScriptReferenceKind::EventTarget,
+                                context,
+                                node,
+                            });
+                        }
+                    }
+                }
+            }
+
+            if node.is_block() {
+                let context = Self::block_context(key).unwrap_or(context);
+                self.walk(node.children(), context, visit);
+            }
+        }
+    }
+
+    /// Walks an entire script file. Scripted effects and triggers are walked in their own context,
+    /// while everything else is only walked once it reaches a block known to hold effects or triggers.
+    pub fn walk_file<'a, F>(&self, file: &'a ScriptFile, visit: &mut F)
+    where
+        F: FnMut(ScriptReference<'a>),
+    {
+        for node in file.nodes.iter().filter(|n| n.is_block()) {
+            match Self::file_context(&file.path) {
+                Some(context) => self.walk(node.children(), context, visit),
+                None => self.walk_unscoped(std::slice::from_ref(node), visit),
+            }
+        }
+    }
+
+    /// Returns the context the top-level blocks of a script file are evaluated in, if any.
+    pub fn file_context(path: &str) -> Option<ScriptContext> {
+        if path.starts_with("common/scripted_effects/") {
+            Some(ScriptContext::Effect)
+        } else if path.starts_with("common/scripted_triggers/") {
+            Some(ScriptContext::Trigger)
+        } else {
+            None
+        }
+    }
+
+    /// Walks an entire script file, calling `visit` for every modifier used in it. Modifiers show up
+    /// in too many kinds of blocks to track them by context, so every key is checked.
+    pub fn walk_modifiers<'a, F>(&self, nodes: &'a [ScriptNode], visit: &mut F)
+    where
+        F: FnMut(ScriptReference<'a>),
+    {
+        for node in nodes {
+            if let Some(id) = self.modifier(node.key()) {
+                visit(ScriptReference {
+                    id,
+                    kind: ScriptReferenceKind::Modifier,
+                    // modifiers aren't evaluated as either, so this is only a placeholder
+                    context: ScriptContext::Effect,
+                    node,
+                });
+            }
+
+            self.walk_modifiers(node.children(), visit);
+        }
+    }
+
+    /// Walks a block of script whose context isn't known, only visiting the contents of blocks
+    /// known to hold effects or triggers.
+    pub fn walk_unscoped<'a, F>(&self, nodes: &'a [ScriptNode], visit: &mut F)
+    where
+        F: FnMut(ScriptReference<'a>),
+    {
+        for node in nodes.iter().filter(|n| n.is_block()) {
+            match Self::block_context(node.key()) {
+                Some(context) => self.walk(node.children(), context, visit),
+                None => self.walk_unscoped(node.children(), visit),
+            }
+        }
+    }
+
+    /// Returns the context the contents of a block with the given key are always evaluated in, if any.
+    pub fn block_context(key: &str) -> Option<ScriptContext> {
+        if TRIGGER_BLOCKS.contains(&key) {
+            Some(ScriptContext::Trigger)
+        } else if EFFECT_BLOCKS.contains(&key) {
+            Some(ScriptContext::Effect)
+        } else {
+            None
+        }
+    }
+}
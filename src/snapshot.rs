@@ -0,0 +1,276 @@
+use std::{cell::RefCell, fs, path::Path, rc::Rc};
+
+use anyhow::{Error, Result};
+use clauser::{
+    data::script_doc_parser::doc_string::{DocString, DocStringSegment},
+    string_table::StringTable,
+};
+use log::info;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::Config,
+    dossier::{CrossReference, DocCategory, DocInfo, Dossier},
+    entry::DocEntry,
+    mapper::SiteMapper,
+    page::{entry_url, GenericListPageBuilder, MaskPage, PageContext, ScopePage},
+    record::EntryRecord,
+};
+
+/// Bumped whenever the layout of a snapshot changes, so old snapshots are rejected instead of
+/// being misread.
+pub const SNAPSHOT_FORMAT_VERSION: u32 = 3;
+
+#[derive(Serialize, Deserialize)]
+pub struct SnapshotCategory {
+    pub id: u64,
+    pub name: String,
+    pub display_name: String,
+}
+
+/// Everything a dossier knows about a version of the game, saved so that it can be compared
+/// against or documented once the game install it came from is gone.
+#[derive(Serialize, Deserialize)]
+pub struct DossierSnapshot {
+    pub format_version: u32,
+    pub info: DocInfo,
+    pub categories: Vec<SnapshotCategory>,
+    pub entries: Vec<EntryRecord>,
+    pub cross_references: Vec<CrossReference>,
+}
+
+impl DossierSnapshot {
+    pub fn new(dossier: Rc<Dossier>) -> Result<DossierSnapshot> {
+        let mut categories: Vec<SnapshotCategory> = dossier
+            .categories()
+            .map(|c| SnapshotCategory {
+                id: c.id(),
+                name: c.name.clone(),
+                display_name: c.display_name.clone(),
+            })
+            .collect();
+        categories.sort_by(|a, b| a.name.cmp(&b.name));
+
+        // sorted so that snapshots of the same game are identical
+        let mut cross_references = dossier.cross_references().to_vec();
+        cross_references.sort_by(|a, b| {
+            (a.from_id, &a.from_property, a.to_id).cmp(&(b.from_id, &b.from_property, b.to_id))
+        });
+
+        Ok(DossierSnapshot {
+            format_version: SNAPSHOT_FORMAT_VERSION,
+            info: dossier.info.clone(),
+            categories,
+            entries: EntryRecord::record_all(dossier.clone())?,
+            cross_references,
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_json::to_string(self)?)?;
+        info!(
+            "saved snapshot of {} entries to {}",
+            self.entries.len(),
+            path.display()
+        );
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<DossierSnapshot> {
+        let snapshot: DossierSnapshot = serde_json::from_str(&fs::read_to_string(path)?)?;
+        if snapshot.format_version != SNAPSHOT_FORMAT_VERSION {
+            return Err(Error::msg(format!(
+                "Snapshot {} has format version {}, but only version {} is supported",
+                path.display(),
+                snapshot.format_version,
+                SNAPSHOT_FORMAT_VERSION
+            )));
+        }
+
+        Ok(snapshot)
+    }
+
+    /// Rebuilds a dossier from this snapshot.
+    pub fn into_dossier(self, config: &Config, mapper: Rc<RefCell<SiteMapper>>) -> Result<Dossier> {
+        let mut dossier = Dossier::new(
+            config.clone(),
+            self.categories
+                .iter()
+                .map(|c| DocCategory::from_id(c.id, &c.name, &c.display_name)),
+            StringTable::new(),
+            self.info,
+            mapper,
+        );
+
+        // scopes and masks are created by their page builders rather than stored as entries
+        let (mut scopes, mut masks, mut entries) = (Vec::new(), Vec::new(), Vec::new());
+        for record in self.entries {
+            match record.category.as_str() {
+                "scopes" => scopes.push(Rc::new(record.name)),
+                "masks" => masks.push(Rc::new(record.name)),
+                _ => {
+                    // like the game's modifiers, which are only listed on their masks' pages
+                    let category_id = match record.category.as_str() {
+                        "modifiers" => None,
+                        _ => self
+                            .categories
+                            .iter()
+                            .find(|c| c.name == record.category)
+                            .map(|c| c.id),
+                    };
+                    entries.push(SnapshotEntry {
+                        record,
+                        category_id,
+                    });
+                }
+            }
+        }
+
+        dossier.add_entries(entries.into_iter())?;
+        for reference in &self.cross_references {
+            dossier.add_reference(&reference.from_property, reference.from_id, reference.to_id);
+        }
+
+        dossier.add_builder(GenericListPageBuilder::<ScopePage>::new(scopes));
+        dossier.add_builder(GenericListPageBuilder::<MaskPage>::new(masks));
+
+        info!("loaded {} entries from snapshot", dossier.entries.len());
+        Ok(dossier)
+    }
+}
+
+/// An entry loaded from a snapshot.
+pub struct SnapshotEntry {
+    record: EntryRecord,
+    category_id: Option<u64>,
+}
+
+impl DocEntry for SnapshotEntry {
+    fn id(&self) -> u64 {
+        self.record.id
+    }
+
+    fn category_id(&self) -> Option<u64> {
+        self.category_id
+    }
+
+    fn name(&self) -> &str {
+        &self.record.name
+    }
+
+    // cross references are restored from the snapshot as a whole
+    fn record_cross_references(&self, _dossier: &mut Dossier) {}
+
+    fn body(&self) -> Option<DocString> {
+        self.record.body.as_ref().map(|body| {
+            // there's no page context here, so links are left for `DocStringSer` to resolve
+            body.to_doc_string(|name, id| DocStringSegment::Link {
+                contents: name.to_owned(),
+                url: entry_url(id),
+            })
+        })
+    }
+
+    fn properties(&self, context: &PageContext, dossier: Rc<Dossier>) -> Vec<(String, DocString)> {
+        self.record
+            .properties
+            .iter()
+            .map(|(name, value)| {
+                (
+                    name.clone(),
+                    value.to_doc_string(|name, id| dossier.link_for_entry(context, name, &id)),
+                )
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use clauser::{
+        data::script_doc_parser::{ScriptDocCategory, ScriptDocEntry},
+        string_table::StringTable,
+    };
+    use itertools::Itertools;
+
+    use crate::{
+        config::Config,
+        dossier::{DocInfo, Dossier},
+        games::{self, GameDocProvider, GameVersion},
+        mapper::SiteMapper,
+        page::{GenericListPageBuilder, MaskPage, ScopePage, UnusedReportPageBuilder},
+        util,
+    };
+
+    use super::DossierSnapshot;
+
+    fn page_paths(dossier: Rc<Dossier>, config: &Config) -> Vec<String> {
+        Dossier::create_pages(dossier, config)
+            .iter()
+            .map(|p| p.info().path)
+            // vanilla usages aren't saved in snapshots, so there's nothing to report
+            .filter(|p| p != "unused")
+            .sorted()
+            .collect()
+    }
+
+    #[test]
+    fn snapshot_builds_the_same_pages() {
+        let config: Config = serde_json::from_value(serde_json::json!({
+            "profiles": [{ "name": "test", "title": "Test", "game": "victoria3" }],
+            "url_scheme": "relative",
+            "output_dir": "out",
+        }))
+        .unwrap();
+        let profile = &config.profiles[0];
+        let mapper = || Rc::new(RefCell::new(SiteMapper::new(config.clone())));
+
+        let entry = |category, name: &str| ScriptDocEntry {
+            id: util::hash(name),
+            category,
+            name: name.to_string(),
+            content: None,
+        };
+
+        let mut live = Dossier::new(
+            config.clone(),
+            games::provider_for_game(&profile.game)
+                .get_categories(profile)
+                .unwrap(),
+            StringTable::new(),
+            DocInfo::new(
+                profile,
+                GameVersion {
+                    version_number: "1.0".into(),
+                    detailed: "1.0".into(),
+                },
+            ),
+            mapper(),
+        );
+        live.add_entries(
+            [
+                entry(ScriptDocCategory::Effects, "add_modifier"),
+                entry(ScriptDocCategory::Triggers, "has_modifier"),
+                entry(ScriptDocCategory::Modifiers, "country_prestige_add"),
+            ]
+            .into_iter(),
+        )
+        .unwrap();
+        live.add_builder(GenericListPageBuilder::<ScopePage>::new(vec![Rc::new(
+            "country".to_string(),
+        )]));
+        live.add_builder(GenericListPageBuilder::<MaskPage>::new(vec![Rc::new(
+            "country".to_string(),
+        )]));
+        live.add_builder(UnusedReportPageBuilder);
+        let live = Rc::new(live);
+
+        let saved = serde_json::to_string(&DossierSnapshot::new(live.clone()).unwrap()).unwrap();
+        let snapshot: DossierSnapshot = serde_json::from_str(&saved).unwrap();
+        let loaded = Rc::new(snapshot.into_dossier(&config, mapper()).unwrap());
+
+        assert_eq!(page_paths(live, &config), page_paths(loaded, &config));
+    }
+}
@@ -1,54 +1,103 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
+use std::io::{self, Write};
 use std::path::Path;
 use std::path::PathBuf;
+use std::process::{Command, Stdio};
 
 use anyhow::Error;
 use anyhow::Result;
 use grass::Options;
 use itertools::Itertools;
+use log::{info, warn};
+use once_cell::sync::Lazy;
+use regex::Regex;
 use serde::Deserialize;
+use serde_json::Value;
+use tempfile::TempDir;
 
-#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, Deserialize)]
-#[repr(u8)]
-pub enum Template {
-    #[serde(rename = "category_list")]
-    CategoryList,
-    #[serde(rename = "list_index")]
-    ListIndex,
-    #[serde(rename = "scope")]
-    Scope,
-    #[serde(rename = "mask")]
-    Mask,
-}
+use crate::archive::{self, ArchiveFormat};
+use crate::config::Config;
 
-impl From<Template> for &str {
-    fn from(value: Template) -> Self {
-        match value {
-            Template::CategoryList => "category_list",
-            Template::Scope => "scope",
-            Template::Mask => "mask",
-            Template::ListIndex => "list_index",
-        }
+/// The name of the template a page is rendered with. Names can be specialised with dots, like
+/// `category_list.effects`, falling back to `category_list` if the theme doesn't have the
+/// specialised template.
+#[derive(PartialEq, Eq, Hash, Clone, Debug)]
+pub struct Template(String);
+
+impl Template {
+    pub fn new(name: &str) -> Template {
+        Template(name.to_string())
+    }
+
+    /// A template that's more specific than `name`, falling back to it.
+    pub fn specialized(name: &str, specialization: &str) -> Template {
+        Template(format!("{}.{}", name, specialization))
+    }
+
+    pub fn name(&self) -> &str {
+        &self.0
+    }
+
+    /// The names to look for, from the most specific to the least.
+    pub fn fallbacks(&self) -> impl Iterator<Item = &str> {
+        let name = self.0.as_str();
+        std::iter::once(name).chain(name.rmatch_indices('.').map(move |(i, _)| &name[..i]))
     }
 }
 
-impl From<&str> for Template {
-    fn from(value: &str) -> Self {
-        match value {
-            "category_list" => Template::CategoryList,
-            "scope" => Template::Scope,
-            "mask" => Template::Mask,
-            "list_index" => Template::ListIndex,
-            _ => panic!(),
+/// The theme's strings in the configured language, with the config's replacements applied.
+#[derive(Clone, Default)]
+pub struct Strings(HashMap<String, String>);
+
+impl Strings {
+    pub fn new<'t>(theme: &'t dyn Theme<'t>, config: &Config) -> Result<Strings> {
+        let mut strings = theme.strings(&config.language)?;
+        strings.extend(config.strings.clone());
+        Ok(Strings(strings))
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).map(|s| s.as_str())
+    }
+
+    /// A string as plain text, with placeholders like `{name}` replaced from `args`. Missing
+    /// strings are replaced by their key, since the text pdxdoc writes itself can't fail to render.
+    pub fn text(&self, key: &str, args: &[(&str, &str)]) -> String {
+        let mut text = match self.get(key) {
+            Some(text) => text.to_string(),
+            None => {
+                warn!("Theme doesn't have a string named {}", key);
+                key.to_string()
+            }
+        };
+
+        for (name, value) in args {
+            text = text.replace(&format!("{{{}}}", name), value);
         }
+
+        text
     }
 }
 
 pub trait Theme<'t> {
-    fn str_for_template(&'t self, template: Template) -> Result<&'t str>;
+    fn str_for_template(&'t self, name: &str) -> Option<&'t str>;
     fn partials(&'t self) -> Vec<(&'t str, &'t str)>;
+    /// The theme's own Handlebars helpers, by name.
+    fn helpers(&'t self) -> Vec<(&'t str, &'t ThemeScript)>;
     fn assets(&'t self) -> &'t Vec<(String, Vec<u8>)>;
+    /// The theme's settings, with any overrides from the config applied.
+    fn settings(&'t self) -> &'t BTreeMap<String, Value>;
+    /// The theme's strings in `language`, falling back to English for any that aren't translated.
+    fn strings(&'t self, language: &str) -> Result<HashMap<String, String>>;
+
+    /// Finds the name of the most specific template this theme has for `template`.
+    fn resolve_template<'a>(&'t self, template: &'a Template) -> Result<&'a str> {
+        template
+            .fallbacks()
+            .find(|n| self.str_for_template(n).is_some())
+            .ok_or(Error::msg(format!("Missing template {}", template.0)))
+    }
 }
 
 /*
@@ -120,139 +169,523 @@ enum GlobOrKeys {
 }
 
 impl GlobOrKeys {
-    pub fn read(&self, dir: &Path) -> HashMap<String, String> {
-        if let Self::Keys(keys) = self {
-            return keys
+    /// Reads the Handlebars files these refer to, checking that each one compiles, so that syntax
+    /// errors point at the file they're in rather than at a page being rendered.
+    pub fn read(&self, dir: &Path) -> Result<HashMap<String, String>> {
+        let files = match self {
+            Self::Keys(keys) => keys
                 .iter()
-                .map(|(k, v)| {
-                    (
-                        k.clone(),
-                        fs::read_to_string(dir.to_path_buf().join(v)).unwrap(),
-                    )
-                })
-                .collect();
+                .map(|(k, v)| (k.clone(), dir.join(v)))
+                .collect_vec(),
+            Self::SingleGlob(glob) => glob_files(&[glob.clone()], dir)?
+                .into_iter()
+                .map(|f| (file_stem(&f), f))
+                .collect_vec(),
+            Self::MultiGlob(globs) => glob_files(globs, dir)?
+                .into_iter()
+                .map(|f| (file_stem(&f), f))
+                .collect_vec(),
+        };
+
+        let mut table = HashMap::new();
+        for (name, path) in files {
+            let source = fs::read_to_string(&path)
+                .map_err(|e| Error::msg(format!("Can't read {}: {}", path.display(), e)))?;
+            handlebars::Template::compile_with_name(&source, path.display().to_string())
+                .map_err(|e| Error::msg(format!("Syntax error in {}: {}", path.display(), e)))?;
+            table.insert(name, source);
         }
 
-        let globs = match self {
-            Self::SingleGlob(glob) => vec![glob],
-            Self::MultiGlob(globs) => globs.iter().collect_vec(),
-            _ => panic!(),
-        };
+        Ok(table)
+    }
+}
 
-        let files = globs
+/// Finds the files in `dir` matching any of `globs`, sorted by path.
+fn glob_files(globs: &[String], dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    for glob in globs {
+        let parsed = wax::Glob::new(glob)
+            .map_err(|e| Error::msg(format!("Invalid glob {:?} in {:?}: {}", glob, dir, e)))?;
+        for entry in parsed.walk(dir) {
+            files.push(entry?.path().to_path_buf());
+        }
+    }
+
+    files.sort();
+    files.dedup();
+    Ok(files)
+}
+
+fn file_stem(path: &Path) -> String {
+    path.file_stem()
+        .and_then(|f| f.to_str())
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// Turns an asset from a theme into the file that's written to the output directory.
+trait AssetProcessor {
+    /// Processes an asset, returning its new path and contents.
+    fn process(&self, path: &Path, bytes: Vec<u8>) -> Result<(PathBuf, Vec<u8>)>;
+}
+
+/// The language every theme's strings are written in, which translations fall back to.
+const DEFAULT_LANGUAGE: &str = "en";
+
+/// The directory the theme settings module appears to be in, which doesn't exist on disk.
+const SETTINGS_DIR: &str = "<pdxdoc>";
+
+/// Colours SCSS knows by name, separated by spaces.
+const NAMED_COLORS: &str =
+    "aliceblue antiquewhite aqua aquamarine azure beige bisque black blanchedalmond blue \
+     blueviolet brown burlywood cadetblue chartreuse chocolate coral cornflowerblue cornsilk \
+     crimson cyan darkblue darkcyan darkgoldenrod darkgray darkgreen darkgrey darkkhaki \
+     darkmagenta darkolivegreen darkorange darkorchid darkred darksalmon darkseagreen \
+     darkslateblue darkslategray darkslategrey darkturquoise darkviolet deeppink deepskyblue \
+     dimgray dimgrey dodgerblue firebrick floralwhite forestgreen fuchsia gainsboro ghostwhite \
+     gold goldenrod gray green greenyellow grey honeydew hotpink indianred indigo ivory khaki \
+     lavender lavenderblush lawngreen lemonchiffon lightblue lightcoral lightcyan \
+     lightgoldenrodyellow lightgray lightgreen lightgrey lightpink lightsalmon lightseagreen \
+     lightskyblue lightslategray lightslategrey lightsteelblue lightyellow lime limegreen linen \
+     magenta maroon mediumaquamarine mediumblue mediumorchid mediumpurple mediumseagreen \
+     mediumslateblue mediumspringgreen mediumturquoise mediumvioletred midnightblue mintcream \
+     mistyrose moccasin navajowhite navy oldlace olive olivedrab orange orangered orchid \
+     palegoldenrod palegreen paleturquoise palevioletred papayawhip peachpuff peru pink plum \
+     powderblue purple rebeccapurple red rosybrown royalblue saddlebrown salmon sandybrown \
+     seagreen seashell sienna silver skyblue slateblue slategray slategrey snow springgreen \
+     steelblue tan teal thistle tomato turquoise violet wheat white whitesmoke yellow yellowgreen \
+     transparent";
+
+static COLOR_FUNCTION_REGEX: Lazy<Regex> = Lazy::new(|| {
+    let number = r"[-+]?(?:\d+(?:\.\d*)?|\.\d+)%?";
+    Regex::new(&format!(
+        r"^(?i)(?:rgba?|hsla?)\(\s*{n}(?:deg)?(?:(?:\s*,\s*|\s+){n}){{2}}(?:\s*[,/]\s*{n})?\s*\)$",
+        n = number
+    ))
+    .unwrap()
+});
+
+/// The files SCSS is compiled from, which is the file system plus a `theme` module holding the
+/// theme's settings as variables, so stylesheets can `@use "theme"` and read `theme.$accent_color`.
+#[derive(Debug)]
+struct ScssFs {
+    settings_path: PathBuf,
+    settings: Vec<u8>,
+}
+
+impl ScssFs {
+    /// Settings named like `accent_color` must be colours, since stylesheets pass them to colour
+    /// functions like `darken`.
+    fn new(settings: &BTreeMap<String, Value>) -> Result<ScssFs> {
+        if let Some((name, value)) = settings
             .iter()
-            .map(|g| wax::Glob::new(&g).unwrap())
-            .flat_map(|g| g.walk(dir).collect_vec())
-            .collect_vec();
+            .filter(|(name, _)| name.ends_with("_color"))
+            .find(|(_, value)| !matches!(value, Value::String(s) if Self::is_color(s)))
+        {
+            return Err(Error::msg(format!(
+                "Theme setting {} must be a colour, like \"#3b7dd8\", \"red\" or \"rgb(59, 125, 216)\", not {}",
+                name, value
+            )));
+        }
 
-        let mut table = HashMap::new();
-        let parent_components_num = dir.components().count();
-        for f in files {
-            let f = f.unwrap();
-            let child_components = f
-                .path()
-                .components()
-                .skip(parent_components_num)
-                .collect_vec();
-            let child_path = PathBuf::from_iter(child_components.into_iter())
-                .to_str()
-                .unwrap()
-                .to_string();
-            table.insert(
-                f.path()
-                    .file_stem()
-                    .and_then(|f| f.to_str())
-                    .unwrap()
-                    .to_string(),
-                fs::read_to_string(f.path()).unwrap(),
-            );
+        let settings = settings
+            .iter()
+            .map(|(name, value)| format!("${}: {};\n", name, Self::scss_value(value)))
+            .join("");
+
+        Ok(ScssFs {
+            settings_path: Path::new(SETTINGS_DIR).join("theme.scss"),
+            settings: settings.into_bytes(),
+        })
+    }
+
+    /// Whether a string is a colour that's safe to write into SCSS as it is: a hex colour like
+    /// `#3b7dd8`, a named colour like `red`, or an `rgb()` or `hsl()` value.
+    fn is_color(s: &str) -> bool {
+        match s.strip_prefix('#') {
+            Some(digits) => {
+                matches!(digits.len(), 3 | 4 | 6 | 8)
+                    && digits.chars().all(|c| c.is_ascii_hexdigit())
+            }
+            None => {
+                NAMED_COLORS
+                    .split_whitespace()
+                    .any(|c| c.eq_ignore_ascii_case(s))
+                    || COLOR_FUNCTION_REGEX.is_match(s)
+            }
         }
+    }
 
-        table
+    /// Writes a setting as an SCSS value. Strings are quoted, except for colours.
+    fn scss_value(value: &Value) -> String {
+        match value {
+            Value::Null => "null".into(),
+            Value::Bool(b) => b.to_string(),
+            Value::Number(n) => n.to_string(),
+            Value::String(s) if Self::is_color(s) => s.clone(),
+            Value::String(s) => format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\"")),
+            Value::Array(values) if values.is_empty() => "()".into(),
+            Value::Array(values) => {
+                format!("({},)", values.iter().map(Self::scss_value).join(", "))
+            }
+            Value::Object(values) => format!(
+                "({})",
+                values
+                    .iter()
+                    .map(|(k, v)| format!(
+                        "{}: {}",
+                        Self::scss_value(&Value::String(k.clone())),
+                        Self::scss_value(v)
+                    ))
+                    .join(", ")
+            ),
+        }
+    }
+}
+
+impl grass::Fs for ScssFs {
+    fn is_dir(&self, path: &Path) -> bool {
+        path.is_dir()
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        path == self.settings_path || path.is_file()
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        match path == self.settings_path {
+            true => Ok(self.settings.clone()),
+            false => fs::read(path),
+        }
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        fs::canonicalize(path)
+    }
+}
+
+/// Compiles SCSS into CSS.
+struct ScssProcessor {
+    /// The directories of the themes being extended, so their stylesheets can be `@use`d.
+    load_paths: Vec<PathBuf>,
+    fs: ScssFs,
+}
+
+impl AssetProcessor for ScssProcessor {
+    fn process(&self, path: &Path, _bytes: Vec<u8>) -> Result<(PathBuf, Vec<u8>)> {
+        // compiled from the path, so that imports are resolved relative to it
+        let options = Options::default()
+            .fs(&self.fs)
+            .load_paths(&self.load_paths)
+            .load_path(SETTINGS_DIR);
+        let compiled = grass::from_path(path, &options)
+            .map_err(|e| Error::msg(format!("Can't compile {}: {}", path.display(), e)))?;
+        Ok((path.with_extension("css"), compiled.into_bytes()))
+    }
+}
+
+/// Pipes an asset through an external command, like a minifier, which reads the asset from stdin
+/// and writes the processed asset to stdout.
+struct CommandProcessor {
+    command: Vec<String>,
+}
+
+impl AssetProcessor for CommandProcessor {
+    fn process(&self, path: &Path, bytes: Vec<u8>) -> Result<(PathBuf, Vec<u8>)> {
+        let (program, args) = self
+            .command
+            .split_first()
+            .ok_or(Error::msg("Asset processor has an empty command"))?;
+
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+
+        // written from another thread while stdout is read, or both sides can block on full pipes
+        let mut stdin = child.stdin.take().unwrap();
+        let writer = std::thread::spawn(move || stdin.write_all(&bytes));
+
+        let output = child.wait_with_output()?;
+        writer
+            .join()
+            .map_err(|_| Error::msg("Asset processor input thread panicked"))??;
+        if !output.status.success() {
+            return Err(Error::msg(format!(
+                "Asset processor {} failed on {:?} with {}",
+                program, path, output.status
+            )));
+        }
+
+        info!("processed asset {:?} with {}", path, program);
+        Ok((path.to_path_buf(), output.stdout))
     }
 }
 
 #[derive(Deserialize)]
 struct PackagedThemeManifest {
     name: String,
+    /// The theme this one is based on, which can be a directory or an archive. This is a path
+    /// relative to the directory containing this theme, so themes kept side by side can extend
+    /// each other by name, whether they're directories or archives. If there's no theme there, it's
+    /// the name of a built-in theme. Anything this theme doesn't define comes from the theme it
+    /// extends.
+    #[serde(default)]
+    extends: Option<String>,
+    #[serde(default)]
     assets: Vec<String>,
-    templates: GlobOrKeys,
-    partials: GlobOrKeys,
+    #[serde(default)]
+    templates: Option<GlobOrKeys>,
+    #[serde(default)]
+    partials: Option<GlobOrKeys>,
+    /// Commands to process assets with, by file extension, like `"js": ["esbuild", "--minify"]`.
+    /// SCSS is always compiled, and any other assets are copied as they are.
+    #[serde(default)]
+    processors: HashMap<String, Vec<String>>,
+    /// Handlebars helpers written in Rhai, by name, like `"plural": "helpers/plural.rhai"`.
+    #[serde(default)]
+    helpers: HashMap<String, String>,
+    /// Settings that can be changed from the config without editing the theme, with their
+    /// defaults. They're available to templates as `theme` and to SCSS as the `theme` module.
+    #[serde(default)]
+    settings: BTreeMap<String, Value>,
+}
+
+/// A script from a theme, along with where it came from so errors can point at it.
+#[derive(Clone)]
+pub struct ThemeScript {
+    pub path: PathBuf,
+    pub source: String,
 }
 
 pub struct PackagedTheme {
     dir: PathBuf,
     manifest: PackagedThemeManifest,
+    parent: Option<Box<PackagedTheme>>,
     assets: Vec<(String, Vec<u8>)>,
-    templates: HashMap<Template, String>,
+    templates: HashMap<String, String>,
     partials: HashMap<String, String>,
+    helpers: HashMap<String, ThemeScript>,
+    settings: BTreeMap<String, Value>,
+    /// The theme's strings, by language and then by key.
+    strings: HashMap<String, HashMap<String, String>>,
+    /// Where the theme was extracted to if it's an archive, which is deleted along with the theme.
+    _extracted: Option<TempDir>,
 }
 
 impl PackagedTheme {
-    pub fn new(dir: &Path) -> anyhow::Result<PackagedTheme> {
-        let manifest_path = dir.clone().join("theme.json");
+    /// The directory of a theme that comes with pdxdoc.
+    pub fn builtin_dir(name: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("themes")
+            .join(name)
+    }
+
+    /// Loads the theme in `path`, which is either a directory or a `.zip` or `.tar.gz` archive,
+    /// overriding its default settings with `settings`. Themes with asset processors run programs
+    /// on this machine, so they're only loaded if `allow_processors` is set.
+    pub fn new(
+        path: &Path,
+        settings: &BTreeMap<String, Value>,
+        allow_processors: bool,
+    ) -> anyhow::Result<PackagedTheme> {
+        let theme = Self::load(path, settings, allow_processors, &mut Vec::new())?;
+
+        if let Some(unknown) = settings.keys().find(|k| !theme.settings.contains_key(*k)) {
+            return Err(Error::msg(format!(
+                "Theme {} has no setting named {} (it has {})",
+                theme.manifest.name,
+                unknown,
+                theme.settings.keys().join(", ")
+            )));
+        }
+
+        Ok(theme)
+    }
+
+    /// Loads a theme and the themes it extends, where `chain` is the themes that extend this one
+    /// and `overrides` is the settings they've set.
+    fn load(
+        path: &Path,
+        overrides: &BTreeMap<String, Value>,
+        allow_processors: bool,
+        chain: &mut Vec<PathBuf>,
+    ) -> anyhow::Result<PackagedTheme> {
+        // archives are loaded from wherever they're extracted to
+        let (extracted, dir) = match ArchiveFormat::for_path(path) {
+            Some(format) if path.is_file() => {
+                let (extracted, dir) = archive::extract(path, format)?;
+                (Some(extracted), dir)
+            }
+            _ => (None, path.to_path_buf()),
+        };
+        let dir = dir.as_path();
+
+        let manifest_path = dir.join("theme.json");
         if !manifest_path.is_file() {
             return Err(Error::msg(format!("Can't find theme.json in {:?}", dir)));
         }
 
+        let canonical = path.canonicalize()?;
+        if chain.contains(&canonical) {
+            return Err(Error::msg(format!("Theme {:?} extends itself", path)));
+        }
+        // the themes this one extends are found next to it, whether it's a directory or an archive
+        let base_dir = canonical.parent().unwrap_or(Path::new("")).to_path_buf();
+        chain.push(canonical);
+
         let manifest =
-            serde_json::from_str::<PackagedThemeManifest>(&fs::read_to_string(&manifest_path)?)?;
+            serde_json::from_str::<PackagedThemeManifest>(&fs::read_to_string(&manifest_path)?)
+                .map_err(|e| Error::msg(format!("Invalid {}: {}", manifest_path.display(), e)))?;
+
+        if !manifest.processors.is_empty() && !allow_processors {
+            return Err(Error::msg(format!(
+                "Theme {} runs programs to process its assets ({}), which must be allowed with allow_theme_processors in the config",
+                manifest.name,
+                manifest.processors.values().filter_map(|c| c.first()).join(", ")
+            )));
+        }
 
-        let asset_files = manifest
-            .assets
-            .iter()
-            .flat_map(|g| wax::Glob::new(&g).unwrap().walk(&dir).collect_vec())
-            .map(|r| r.unwrap())
-            .collect_vec();
+        let mut settings = manifest.settings.clone();
+        settings.extend(overrides.iter().map(|(k, v)| (k.clone(), v.clone())));
+
+        // the parent is given this theme's settings too, so its stylesheets see the same values
+        let parent = match &manifest.extends {
+            Some(extends) => {
+                let relative = base_dir.join(extends);
+                let parent_dir = match relative.join("theme.json").is_file()
+                    || (relative.is_file() && ArchiveFormat::for_path(&relative).is_some())
+                {
+                    true => relative,
+                    false => Self::builtin_dir(extends),
+                };
+                Some(Box::new(Self::load(
+                    &parent_dir,
+                    &settings,
+                    allow_processors,
+                    chain,
+                )?))
+            }
+            None => None,
+        };
 
-        let mut assets = Vec::new();
+        if let Some(parent) = &parent {
+            settings = parent.settings.clone();
+        }
 
-        let options = Options::default();
-        let parent_components_num = dir.components().count();
-        for sass in asset_files
-            .iter()
-            .filter(|f| f.path().extension().map(|e| e == "scss").unwrap_or(false))
-        {
-            let compiled = grass::from_path(sass.path(), &options)?;
-            let child_components = sass
-                .path()
-                .components()
-                .skip(parent_components_num)
-                .collect_vec();
-            let mut child_path = PathBuf::from_iter(child_components.into_iter());
-            child_path.set_extension("css");
-
-            assets.push((
-                child_path.to_str().unwrap().to_string(),
-                compiled.as_bytes().to_vec(),
-            ))
+        let asset_files = glob_files(&manifest.assets, dir)?;
+
+        let mut processors: HashMap<String, Box<dyn AssetProcessor>> = HashMap::new();
+        processors.insert(
+            "scss".into(),
+            Box::new(ScssProcessor {
+                load_paths: parent
+                    .iter()
+                    .flat_map(|p| p.dirs())
+                    .map(|d| d.to_path_buf())
+                    .collect(),
+                fs: ScssFs::new(&settings)?,
+            }),
+        );
+        for (extension, command) in &manifest.processors {
+            processors.insert(
+                extension.clone(),
+                Box::new(CommandProcessor {
+                    command: command.clone(),
+                }),
+            );
+        }
+
+        // start with everything from the parent, and replace whatever this theme defines itself
+        let mut assets = parent
+            .as_ref()
+            .map(|p| p.assets.clone())
+            .unwrap_or_default();
+        for path in asset_files {
+            let bytes = fs::read(&path)?;
+            let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+            let (out_path, bytes) = match processors.get(extension) {
+                Some(processor) => processor.process(&path, bytes)?,
+                None => (path.clone(), bytes),
+            };
+
+            let child_path = out_path.strip_prefix(dir)?.to_str().unwrap().to_string();
+            assets.retain(|(p, _)| *p != child_path);
+            assets.push((child_path, bytes));
         }
 
-        let templates = manifest
-            .templates
-            .read(dir)
-            .into_iter()
-            .map(|(k, v)| (Template::from(k.as_str()), v))
-            .collect();
+        let mut templates = parent
+            .as_ref()
+            .map(|p| p.templates.clone())
+            .unwrap_or_default();
+        if let Some(own) = &manifest.templates {
+            templates.extend(own.read(dir)?);
+        }
+
+        let mut partials = parent
+            .as_ref()
+            .map(|p| p.partials.clone())
+            .unwrap_or_default();
+        if let Some(own) = &manifest.partials {
+            partials.extend(own.read(dir)?);
+        }
+
+        let mut helpers = parent
+            .as_ref()
+            .map(|p| p.helpers.clone())
+            .unwrap_or_default();
+        for (name, script) in &manifest.helpers {
+            let path = dir.join(script);
+            let source = fs::read_to_string(&path)
+                .map_err(|e| Error::msg(format!("Can't read {}: {}", path.display(), e)))?;
+            helpers.insert(name.clone(), ThemeScript { path, source });
+        }
+
+        // strings are merged key by key, so a theme can replace a few without translating them all
+        let mut strings = parent
+            .as_ref()
+            .map(|p| p.strings.clone())
+            .unwrap_or_default();
+        if dir.join("strings").is_dir() {
+            for path in glob_files(&["strings/*.json".into()], dir)? {
+                let table =
+                    serde_json::from_str::<HashMap<String, String>>(&fs::read_to_string(&path)?)
+                        .map_err(|e| Error::msg(format!("Invalid {}: {}", path.display(), e)))?;
+                strings.entry(file_stem(&path)).or_default().extend(table);
+            }
+        }
 
-        let partials = manifest.partials.read(dir);
+        chain.pop();
 
         Ok(PackagedTheme {
             dir: dir.to_path_buf(),
             manifest,
+            parent,
             assets,
             templates,
             partials,
+            helpers,
+            settings,
+            strings,
+            _extracted: extracted,
         })
     }
+
+    /// The directory of this theme, followed by those of the themes it extends.
+    fn dirs(&self) -> Vec<&Path> {
+        let mut dirs = vec![self.dir.as_path()];
+        if let Some(parent) = &self.parent {
+            dirs.extend(parent.dirs());
+        }
+
+        dirs
+    }
 }
 
 impl<'t> Theme<'t> for PackagedTheme {
-    fn str_for_template(&'t self, template: Template) -> Result<&'t str> {
-        self.templates
-            .get(&template)
-            .map(|s| s.as_str())
-            .ok_or(Error::msg(format!("Missing template {:?}", template)))
+    fn str_for_template(&'t self, name: &str) -> Option<&'t str> {
+        self.templates.get(name).map(|s| s.as_str())
     }
 
     fn partials(&'t self) -> Vec<(&'t str, &'t str)> {
@@ -262,7 +695,41 @@ impl<'t> Theme<'t> for PackagedTheme {
             .collect_vec()
     }
 
+    fn helpers(&'t self) -> Vec<(&'t str, &'t ThemeScript)> {
+        self.helpers
+            .iter()
+            .map(|(n, s)| (n.as_str(), s))
+            .sorted_by_key(|(n, _)| *n)
+            .collect_vec()
+    }
+
     fn assets(&'t self) -> &'t Vec<(String, Vec<u8>)> {
         &self.assets
     }
+
+    fn settings(&'t self) -> &'t BTreeMap<String, Value> {
+        &self.settings
+    }
+
+    fn strings(&'t self, language: &str) -> Result<HashMap<String, String>> {
+        let mut strings = self
+            .strings
+            .get(DEFAULT_LANGUAGE)
+            .cloned()
+            .unwrap_or_default();
+
+        if language != DEFAULT_LANGUAGE {
+            let translated = self.strings.get(language).ok_or_else(|| {
+                Error::msg(format!(
+                    "Theme {} has no strings for language {} (it has {})",
+                    self.manifest.name,
+                    language,
+                    self.strings.keys().sorted().join(", ")
+                ))
+            })?;
+            strings.extend(translated.iter().map(|(k, v)| (k.clone(), v.clone())));
+        }
+
+        Ok(strings)
+    }
 }
@@ -0,0 +1,137 @@
+use std::{collections::HashMap, path::Path};
+
+use anyhow::Result;
+use clauser::value::ValueOwned;
+use log::{debug, info, warn};
+
+use crate::script::{find_script_files, ScriptFile, ScriptReferenceKind, ScriptVocabulary};
+
+/// Snippets longer than this many lines aren't used as examples.
+const MAX_EXAMPLE_LINES: usize = 15;
+
+/// A real use of an entry in the game's script files.
+pub struct UsageExample {
+    pub path: String,
+    pub line: usize,
+    pub code: ValueOwned,
+}
+
+/// Everything we know about how entries are used in the game's script files.
+#[derive(Default)]
+pub struct UsageIndex {
+    examples: HashMap<u64, Vec<UsageExample>>,
+    /// The number of uses of each tracked entry, and what kind of entry it is.
+    counts: HashMap<u64, (ScriptReferenceKind, usize)>,
+}
+
+impl UsageIndex {
+    pub fn examples(&self, id: u64) -> &[UsageExample] {
+        self.examples.get(&id).map(|e| e.as_slice()).unwrap_or(&[])
+    }
+
+    /// Returns the number of times an entry is used, or None if its usage isn't tracked.
+    pub fn count(&self, id: u64) -> Option<usize> {
+        self.counts.get(&id).map(|(_, count)| *count)
+    }
+
+    /// Returns the IDs of every tracked entry that's never used, along with what kind of entry it is.
+    pub fn unused(&self) -> Vec<(u64, ScriptReferenceKind)> {
+        self.counts
+            .iter()
+            .filter(|(_, (_, count))| *count == 0)
+            .map(|(id, (kind, _))| (*id, *kind))
+            .collect()
+    }
+}
+
+/// Scans script files for uses of effects, triggers, event targets and modifiers.
+pub struct UsageHarvester<'v> {
+    vocabulary: &'v ScriptVocabulary,
+    max_examples: usize,
+}
+
+impl<'v> UsageHarvester<'v> {
+    pub fn new(vocabulary: &'v ScriptVocabulary, max_examples: usize) -> UsageHarvester<'v> {
+        UsageHarvester {
+            vocabulary,
+            max_examples,
+        }
+    }
+
+    pub fn harvest(&self, root: &Path) -> Result<UsageIndex> {
+        let mut index = UsageIndex {
+            examples: HashMap::new(),
+            counts: self
+                .vocabulary
+                .ids()
+                .map(|(id, kind)| (id, (kind, 0)))
+                .collect(),
+        };
+
+        let mut spares: HashMap<u64, Vec<UsageExample>> = HashMap::new();
+        let files = find_script_files(root, "{common,events}/**/*.txt")?;
+        info!("scanning {} script files for usages", files.len());
+
+        for (disk_path, path) in files {
+            let file = match ScriptFile::read(&disk_path, &path) {
+                Ok(file) => file,
+                Err(e) => {
+                    warn!("skipping script file {}: {}", path, e);
+                    continue;
+                }
+            };
+
+            self.vocabulary.walk_modifiers(&file.nodes, &mut |r| {
+                if let Some((_, count)) = index.counts.get_mut(&r.id) {
+                    *count += 1;
+                }
+            });
+
+            self.vocabulary.walk_file(&file, &mut |r| {
+                if let Some((_, count)) = index.counts.get_mut(&r.id) {
+                    *count += 1;
+                }
+
+                if r.node.end_line - r.node.line >= MAX_EXAMPLE_LINES {
+                    return;
+                }
+
+                // examples from different files are preferred, so ones from a file that's already
+                // been used are kept aside to top up with once every file has been seen
+                let examples = index.examples.entry(r.id).or_default();
+                if examples.len() >= self.max_examples {
+                    return;
+                }
+
+                let examples = match examples.iter().any(|e| e.path == file.path) {
+                    true => spares.entry(r.id).or_default(),
+                    false => examples,
+                };
+                if examples.len() >= self.max_examples {
+                    return;
+                }
+
+                let snippet = file.snippet(r.node);
+                match clauser::de::from_str::<ValueOwned>(&snippet) {
+                    Ok(code) => examples.push(UsageExample {
+                        path: file.path.clone(),
+                        line: r.node.line,
+                        code,
+                    }),
+                    Err(e) => debug!(
+                        "can't use {}:{} as an example: {:?}",
+                        file.path, r.node.line, e
+                    ),
+                }
+            });
+        }
+
+        for (id, spares) in spares {
+            let examples = index.examples.entry(id).or_default();
+            let missing = self.max_examples.saturating_sub(examples.len());
+            examples.extend(spares.into_iter().take(missing));
+        }
+
+        Ok(index)
+    }
+}
@@ -1,5 +1,5 @@
 use std::cell::RefCell;
-use std::hash::{DefaultHasher, Hash, Hasher};
+use std::hash::Hasher;
 use std::rc::Rc;
 
 use clauser::data::script_doc_parser::doc_string::{DocString, DocStringSegment};
@@ -11,18 +11,52 @@ use syntax_highlight::SyntaxHighlighter;
 
 use crate::config::PaginationMode;
 use crate::mapper::SiteMapper;
-use crate::page::Page;
+use crate::page::{Page, ENTRY_URL_PREFIX};
 
 use anyhow::{Error, Result};
 
+mod stable_hash;
 mod syntax_highlight;
 
-pub fn hash<T: Hash>(item: &T) -> u64 {
-    let mut s = DefaultHasher::default();
-    item.hash(&mut s);
+pub use stable_hash::{StableHash, StableHasher};
+
+/// Hashes an item into an ID. IDs are stable, so the same input always produces the same ID no
+/// matter which build of pdxdoc produced it, and they can be saved in snapshots.
+pub fn hash<T: StableHash + ?Sized>(item: &T) -> u64 {
+    let mut s = StableHasher::default();
+    item.stable_hash(&mut s);
     s.finish()
 }
 
+/// Text that ends the current paragraph of a `DocString`, so the next text starts a new one.
+const PARAGRAPH_BREAK: &str = "\n\n";
+
+/// Builds a `DocString` of several paragraphs of HTML.
+pub fn paragraphs<I: IntoIterator<Item = String>>(paragraphs: I) -> DocString {
+    let segments = paragraphs
+        .into_iter()
+        .map(|contents| DocStringSegment::Text { contents });
+    DocString::new_from_iter(
+        Itertools::intersperse(
+            segments,
+            DocStringSegment::Text {
+                contents: PARAGRAPH_BREAK.to_string(),
+            },
+        ),
+        None,
+    )
+}
+
+/// A comma separated list of items, each shown as code.
+pub fn code_list<'a>(items: impl Iterator<Item = &'a String>) -> DocString {
+    DocString::new_from_iter(
+        items.map(|i| DocStringSegment::Text {
+            contents: format!("<code>{}</code>", html_escape(i)),
+        }),
+        Some(", "),
+    )
+}
+
 pub fn humanize_camel_case(text: &str) -> String {
     let mut s = String::with_capacity(text.len());
     let mut make_upper = true;
@@ -86,6 +120,13 @@ impl DocStringSer {
         segment: &DocStringSegment,
     ) -> Result<(), Error> {
         match segment {
+            DocStringSegment::Text { contents } if contents == PARAGRAPH_BREAK => {
+                if *in_para {
+                    *in_para = false;
+                    s.push_str("</p>");
+                }
+                return Ok(());
+            }
             DocStringSegment::Code { .. } | DocStringSegment::RawCode { .. } => {
                 if *in_para {
                     *in_para = false;
@@ -100,14 +141,42 @@ impl DocStringSer {
             }
         }
 
+        match segment {
+            DocStringSegment::Link { contents, url } if url.starts_with(ENTRY_URL_PREFIX) => {
+                s.push_str(&Self::entry_link_html(page_id, &mapper, contents, url))
+            }
+            _ => s.push_str(&Self::segment_html(segment)?),
+        }
+        Ok(())
+    }
+
+    /// Renders a link to an entry by ID as a link relative to the page being rendered.
+    fn entry_link_html(
+        page_id: u64,
+        mapper: &RefCell<SiteMapper>,
+        contents: &str,
+        url: &str,
+    ) -> String {
+        let target = url[ENTRY_URL_PREFIX.len()..]
+            .parse::<u64>()
+            .ok()
+            .and_then(|id| mapper.borrow().entry_url_from_page(page_id, id));
+        match target {
+            Some(url) => format!("<a href=\"{}\">{}</a>", url, contents),
+            None => {
+                warn!("link to {} doesn't point to any page", url);
+                contents.to_owned()
+            }
+        }
+    }
+
+    /// Renders a single segment to HTML, without opening or closing any paragraphs around it.
+    pub fn segment_html(segment: &DocStringSegment) -> Result<String, Error> {
+        let mut s = String::new();
         match segment {
             DocStringSegment::Text { contents } => Ok(s.push_str(contents)),
-            DocStringSegment::Code { contents } => SyntaxHighlighter::to_html(s, contents),
+            DocStringSegment::Code { contents } => SyntaxHighlighter::to_html(&mut s, contents),
             DocStringSegment::RawCode { contents } => {
-                if *in_para {
-                    *in_para = false;
-                    s.push_str("</p>");
-                }
                 Ok(s.push_str(&format!("<div class=\"pd-raw-code\">{}</div>", contents)))
             }
             DocStringSegment::Symbol { identifier, .. } => {
@@ -129,7 +198,7 @@ impl DocStringSer {
             }
         }?;
 
-        Ok(())
+        Ok(s)
     }
 
     pub fn to_html(&self) -> Result<String, Error> {
@@ -0,0 +1,121 @@
+use std::hash::Hasher;
+
+use clauser::data::script_doc_parser::ScriptDocCategory;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/// A 64-bit FNV-1a hasher. Unlike `DefaultHasher`, its output is fixed, so it doesn't change
+/// between Rust releases or platforms.
+pub struct StableHasher(u64);
+
+impl Default for StableHasher {
+    fn default() -> Self {
+        StableHasher(FNV_OFFSET_BASIS)
+    }
+}
+
+impl Hasher for StableHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(FNV_PRIME);
+        }
+    }
+}
+
+/// Something that can be turned into an ID. Unlike `Hash`, the bytes fed to the hasher are spelled
+/// out here rather than left to the standard library, which makes no promise to keep them the same
+/// between releases.
+pub trait StableHash {
+    fn stable_hash(&self, hasher: &mut StableHasher);
+}
+
+impl StableHash for str {
+    fn stable_hash(&self, hasher: &mut StableHasher) {
+        hasher.write(self.as_bytes());
+    }
+}
+
+impl StableHash for String {
+    fn stable_hash(&self, hasher: &mut StableHasher) {
+        self.as_str().stable_hash(hasher);
+    }
+}
+
+impl StableHash for [u8] {
+    fn stable_hash(&self, hasher: &mut StableHasher) {
+        hasher.write(self);
+    }
+}
+
+impl StableHash for Vec<u8> {
+    fn stable_hash(&self, hasher: &mut StableHasher) {
+        self.as_slice().stable_hash(hasher);
+    }
+}
+
+impl StableHash for u64 {
+    fn stable_hash(&self, hasher: &mut StableHasher) {
+        hasher.write(&self.to_le_bytes());
+    }
+}
+
+impl StableHash for usize {
+    fn stable_hash(&self, hasher: &mut StableHasher) {
+        (*self as u64).stable_hash(hasher);
+    }
+}
+
+impl<T: StableHash + ?Sized> StableHash for &T {
+    fn stable_hash(&self, hasher: &mut StableHasher) {
+        (**self).stable_hash(hasher);
+    }
+}
+
+/// The first item of a pair is hashed on its own and fed in as a `u64`, so that `("ab", "c")` and
+/// `("a", "bc")` don't collide.
+impl<A: StableHash, B: StableHash> StableHash for (A, B) {
+    fn stable_hash(&self, hasher: &mut StableHasher) {
+        let mut first = StableHasher::default();
+        self.0.stable_hash(&mut first);
+        hasher.write(&first.finish().to_le_bytes());
+        self.1.stable_hash(hasher);
+    }
+}
+
+impl StableHash for ScriptDocCategory {
+    fn stable_hash(&self, hasher: &mut StableHasher) {
+        let name = match self {
+            ScriptDocCategory::CustomLocalization => "CustomLocalization",
+            ScriptDocCategory::Effects => "Effects",
+            ScriptDocCategory::EventTargets => "EventTargets",
+            ScriptDocCategory::Modifiers => "Modifiers",
+            ScriptDocCategory::OnActions => "OnActions",
+            ScriptDocCategory::Triggers => "Triggers",
+        };
+        name.stable_hash(hasher);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clauser::data::script_doc_parser::ScriptDocCategory;
+
+    use crate::util::hash;
+
+    // IDs end up in snapshots and page URLs, so these must never change
+    #[test]
+    fn ids_are_pinned() {
+        assert_eq!(hash(""), 0xcbf29ce484222325);
+        assert_eq!(hash("a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(hash("SCOPES"), 0xaf824dc03c4d0928);
+        assert_eq!(hash(&format!("event_{}", "test.1")), 0xb93e5e210ebb76fd);
+        assert_eq!(hash(&("ENTRY", 42u64)), 0xff474aa6b0f80420);
+        assert_eq!(hash(&ScriptDocCategory::Effects), hash("Effects"));
+    }
+}